@@ -0,0 +1,249 @@
+use chrono::{Datelike, Duration, Local, NaiveDate, TimeZone, Timelike, Utc};
+use clap::ArgMatches;
+use failure::{bail, format_err};
+use pomodorust::config::Cfg;
+use pomodorust::control;
+use pomodorust::database::{Aggregate, Filter, GroupBy, Pomodoro, Statistic, Worklog};
+use pomodorust::export::{self, Breaks, Rounding};
+use pomodorust::import::{import_csv, import_timew, parse_local_timestamp, ColumnMapping};
+use pomodorust::report::{hours, Report, DEFAULT_TEMPLATE};
+use pomodorust::status::{self, Status};
+use std::fs::{self, File};
+use std::io::{self, Write};
+
+pub fn status(args: &ArgMatches) -> Result<(), failure::Error> {
+    let status = Status::read();
+    let text = args.value_of("text").unwrap_or(status::DEFAULT_FORMAT);
+    match args.value_of("format") {
+        Some("i3bar") => println!("{}", status.i3bar(text)),
+        Some("waybar") => println!("{}", status.waybar(text)),
+        Some(format) => println!("{}", status.format(format)),
+        None => println!("{}", status.format(text)),
+    }
+    Ok(())
+}
+
+pub fn ctl(args: &ArgMatches) -> Result<(), failure::Error> {
+    let mut line = String::from(args.value_of("command").unwrap());
+    if let Some(ticket) = args.value_of("ticket") {
+        line = format!("{} {}", line, ticket);
+    }
+    let answer = control::send(&line)?;
+    if answer.starts_with("error") {
+        bail!("{}", answer);
+    }
+    println!("{}", answer);
+    Ok(())
+}
+
+pub fn stats(cfg: &Cfg, args: &ArgMatches) -> Result<(), failure::Error> {
+    let filter = Filter {
+        since: optional_date_arg(args, "since")?,
+        until: optional_date_arg(args, "until")?,
+        ticket: args.value_of("ticket").map(String::from),
+    };
+    let group_by: GroupBy = args.value_of("group-by").unwrap().parse()?;
+    let aggregates = Aggregate::aggregates(&cfg.conn, &filter, group_by)?;
+    if let Some("json") = args.value_of("format") {
+        println!("{}", serde_json::to_string_pretty(&aggregates)?);
+        return Ok(());
+    }
+    let label = match group_by {
+        GroupBy::Day => "Day",
+        GroupBy::Week => "Week",
+        GroupBy::Month => "Month",
+        GroupBy::Ticket => "Ticket",
+    };
+    println!("{:<16} {:>9} {:>8}", label, "Pomodoros", "Time");
+    for agg in &aggregates {
+        let group = agg.group.as_deref().unwrap_or("-");
+        println!("{:<16} {:>9} {:>8}", group, agg.count, hours(agg.duration));
+    }
+    let count = aggregates.iter().map(|agg| agg.count).sum::<i64>();
+    let duration = aggregates.iter().map(|agg| agg.duration).sum();
+    println!("{:<16} {:>9} {:>8}", "Total", count, hours(duration));
+    Ok(())
+}
+
+pub fn report(cfg: &Cfg, args: &ArgMatches) -> Result<(), failure::Error> {
+    let (since, until) = match optional_date_arg(args, "since")? {
+        Some(since) => (since, date_arg(args, "until")?),
+        None => {
+            let day = date_arg(args, "date")?;
+            let monday = day - Duration::days(day.weekday().num_days_from_monday().into());
+            (monday, monday + Duration::days(6))
+        }
+    };
+    let template = match args.value_of_os("template") {
+        Some(path) => fs::read_to_string(path)?,
+        None => String::from(DEFAULT_TEMPLATE),
+    };
+    // the long break plus a minute of slack still continues a streak
+    let max_gap = Duration::from_std(cfg.long_break)? + Duration::minutes(1);
+    let report = Report::gather(&cfg.conn, since, until, max_gap)?;
+    let mut out: Box<dyn Write> = match args.value_of_os("output") {
+        Some(path) => Box::new(File::create(path)?),
+        None => Box::new(io::stdout()),
+    };
+    writeln!(out, "{}", report.render(&template).trim_end())?;
+    Ok(())
+}
+
+pub fn log(cfg: &Cfg, args: &ArgMatches) -> Result<(), failure::Error> {
+    let duration = match args.value_of("duration") {
+        Some(mins) => match mins.parse::<i64>() {
+            Ok(mins) if mins > 0 => mins,
+            _ => bail!("Minutes must be a positive number, not '{}'.", mins),
+        },
+        None => (cfg.working.as_secs() / 60) as i64,
+    };
+    let start = match args.value_of("start") {
+        Some(text) => parse_local_timestamp(text)?,
+        // a pomodoro that just finished
+        None => Utc::now().naive_utc().with_nanosecond(0).unwrap() - Duration::minutes(duration),
+    };
+    Statistic::manual(
+        start,
+        duration,
+        text_arg(args, "ticket"),
+        text_arg(args, "note"),
+    )
+    .insert(&cfg.conn)?;
+    println!(
+        "Recorded {} min pomodoro started at {}.",
+        duration,
+        Local.from_utc_datetime(&start).format("%Y-%m-%d %H:%M")
+    );
+    Ok(())
+}
+
+pub fn edit(cfg: &Cfg, args: &ArgMatches) -> Result<(), failure::Error> {
+    let id = id_arg(args)?;
+    if !args.is_present("ticket") && !args.is_present("note") {
+        bail!("Nothing to edit, give a --ticket or a --note.");
+    }
+    if args.is_present("ticket")
+        && Statistic::set_ticket(&cfg.conn, id, text_arg(args, "ticket"))? == 0
+    {
+        bail!("There is no pomodoro with ID {}.", id);
+    }
+    if args.is_present("note") && Statistic::set_note(&cfg.conn, id, text_arg(args, "note"))? == 0 {
+        bail!("There is no pomodoro with ID {}.", id);
+    }
+    Ok(())
+}
+
+pub fn delete(cfg: &Cfg, args: &ArgMatches) -> Result<(), failure::Error> {
+    let id = id_arg(args)?;
+    if Statistic::delete(&cfg.conn, id)? == 0 {
+        bail!("There is no pomodoro with ID {}.", id);
+    }
+    println!("Deleted pomodoro {}, `restore {}` brings it back.", id, id);
+    Ok(())
+}
+
+pub fn restore(cfg: &Cfg, args: &ArgMatches) -> Result<(), failure::Error> {
+    let id = id_arg(args)?;
+    if Statistic::restore(&cfg.conn, id)? == 0 {
+        bail!("There is no deleted pomodoro with ID {}.", id);
+    }
+    Ok(())
+}
+
+pub fn purge(cfg: &Cfg) -> Result<(), failure::Error> {
+    let purged = Statistic::purge(&cfg.conn)?;
+    println!("Purged {} deleted pomodoros.", purged);
+    Ok(())
+}
+
+pub fn import(cfg: &Cfg, args: &ArgMatches) -> Result<(), failure::Error> {
+    let mapping = match args.value_of("columns") {
+        Some(spec) => ColumnMapping::parse(spec)?,
+        None => ColumnMapping::default(),
+    };
+    let dry_run = args.is_present("dry-run");
+    let file = File::open(args.value_of_os("file").unwrap())?;
+    let report = match args.value_of("format") {
+        Some("timew") => import_timew(&cfg.conn, file, dry_run)?,
+        _ => import_csv(&cfg.conn, file, &mapping, dry_run)?,
+    };
+    for stat in &report.inserted {
+        println!(
+            "{} {} {} min {} {}",
+            if dry_run { "would insert" } else { "inserted" },
+            stat.created_at.unwrap(),
+            stat.duration,
+            stat.ticket_id.as_deref().unwrap_or_default(),
+            stat.note.as_deref().unwrap_or_default(),
+        );
+    }
+    println!(
+        "{} pomodoros {}, {} duplicates skipped.",
+        report.inserted.len(),
+        if dry_run { "to insert" } else { "inserted" },
+        report.duplicates.len()
+    );
+    Ok(())
+}
+
+pub fn export(cfg: &Cfg, args: &ArgMatches) -> Result<(), failure::Error> {
+    let since = date_arg(args, "since")?;
+    let until = date_arg(args, "until")?;
+    let pomodoros = Pomodoro::pomodoros_between(&cfg.conn, since, until)?;
+    let mut out: Box<dyn Write> = match args.value_of_os("output") {
+        Some(path) => Box::new(File::create(path)?),
+        None => Box::new(io::stdout()),
+    };
+    let breaks = Breaks {
+        short: chrono::Duration::from_std(cfg.short_break)?,
+        long: chrono::Duration::from_std(cfg.long_break)?,
+    };
+    let breaks = if args.is_present("breaks") {
+        Some(&breaks)
+    } else {
+        None
+    };
+    match args.value_of("format") {
+        Some("jira") => {
+            let worklogs = Worklog::worklogs_between(&cfg.conn, since, until)?;
+            let rounding = Rounding {
+                step_mins: args.value_of("round").unwrap().parse()?,
+                mode: args.value_of("rounding").unwrap().parse()?,
+            };
+            let author = match args.value_of("author") {
+                Some(author) => author.to_string(),
+                None => std::env::var("USER").unwrap_or_default(),
+            };
+            export::jira_worklog(&mut out, &worklogs, &rounding, &author)?;
+        }
+        Some("org") => export::org(&mut out, &pomodoros)?,
+        Some("timew") => export::timew(&mut out, &pomodoros)?,
+        _ => export::ics(&mut out, &pomodoros, breaks)?,
+    }
+    Ok(())
+}
+
+fn date_arg(args: &ArgMatches, name: &str) -> Result<NaiveDate, failure::Error> {
+    Ok(optional_date_arg(args, name)?.unwrap_or_else(|| Utc::today().naive_utc()))
+}
+
+fn optional_date_arg(args: &ArgMatches, name: &str) -> Result<Option<NaiveDate>, failure::Error> {
+    match args.value_of(name) {
+        Some(text) => Ok(Some(NaiveDate::parse_from_str(text, "%Y-%m-%d")?)),
+        None => Ok(None),
+    }
+}
+
+fn id_arg(args: &ArgMatches) -> Result<i32, failure::Error> {
+    let id = args.value_of("id").unwrap();
+    id.parse()
+        .map_err(|_| format_err!("'{}' is not a pomodoro ID.", id))
+}
+
+// an empty value clears the field
+fn text_arg(args: &ArgMatches, name: &str) -> Option<String> {
+    args.value_of(name)
+        .map(str::trim)
+        .filter(|text| !text.is_empty())
+        .map(String::from)
+}
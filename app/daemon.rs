@@ -0,0 +1,290 @@
+use crate::ui;
+use clap::ArgMatches;
+use failure::bail;
+use pomodorust::config::{runtime_dir, Cfg, QUIT_KEY};
+use pomodorust::control::{self, Client};
+use pomodorust::desktop::Desktop;
+use pomodorust::events::{Event, EventSource, Events};
+use pomodorust::http;
+use pomodorust::lock::{self, Lock};
+use pomodorust::metrics::{self, Sample};
+use pomodorust::notify;
+use pomodorust::render;
+use pomodorust::state::{self, App};
+use pomodorust::status::Status;
+use pomodorust::timer::Transition;
+use pomodorust::webhook::{self, Webhooks};
+use serde_json::json;
+use std::env;
+use std::fs::File;
+use std::io;
+use std::os::unix::process::CommandExt;
+use std::path::Path;
+use std::process::{self, Stdio};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+use termion::event::Key;
+
+/// Starts the timer in the background, `attach` shows it in a terminal.
+pub fn daemon(cfg: &Cfg, args: &ArgMatches) -> Result<(), failure::Error> {
+    let force = args.is_present("force");
+    if args.is_present("foreground") {
+        return run(cfg, force, http::Address::of(args));
+    }
+    if Client::connect().is_ok() {
+        bail!("A timer is already running, `pomodorust attach` shows it.");
+    }
+    if let (Some(pid), false) = (lock::holder(&cfg.db_path), force) {
+        bail!(
+            "Another timer (PID {}) is running on {}, `--force` starts anyway.",
+            pid,
+            cfg.db_path.display()
+        );
+    }
+    // the same command line again, in a session of its own so that closing
+    // the terminal does not hang it up
+    let mut child = process::Command::new(env::current_exe()?);
+    child
+        .args(env::args_os().skip(1))
+        .arg("--foreground")
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(File::create(runtime_dir()?.join("daemon.log"))?);
+    unsafe {
+        child.pre_exec(|| {
+            libc::setsid();
+            Ok(())
+        });
+    }
+    child.spawn()?;
+    // return once it answers, so that `attach` can follow right away
+    for _ in 0..50 {
+        if Client::connect().is_ok() {
+            println!(
+                "Timer started, `pomodorust attach` shows it and `pomodorust ctl stop` stops it."
+            );
+            return Ok(());
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+    bail!(
+        "The timer did not start, see {}.",
+        runtime_dir()?.join("daemon.log").display()
+    )
+}
+
+// the timer without a terminal, controlled through the socket only
+fn run(cfg: &Cfg, force: bool, http: Option<http::Address>) -> Result<(), failure::Error> {
+    let _lock = Lock::acquire(&cfg.db_path, force)?;
+    let mut events = Events::headless();
+    if let Some(address) = http {
+        http::serve(address, cfg.db_path.clone(), events.sender())?;
+    }
+    control::listen(events.sender())?;
+    let mut app = App::new(cfg);
+    let mut reactions = Reactions::new(cfg, &events)?;
+    let result = run_timer(&mut app, cfg, &mut events, &mut reactions, None);
+    let _ = Status::remove();
+    control::unlink();
+    result
+}
+
+/// The event loop of the timer with its reactions, in a terminal under the
+/// given heading or without one.
+pub fn run_timer<S: EventSource>(
+    app: &mut App,
+    cfg: &Cfg,
+    events: &mut S,
+    reactions: &mut Reactions,
+    mut screen: Option<(&mut ui::Tui, &str)>,
+) -> Result<(), failure::Error> {
+    let mut title = screen
+        .as_ref()
+        .map(|_| ui::Title::new(&cfg.title, cfg.notifications.urgent));
+    state::run_timer(app, cfg, events, |app| {
+        for transition in reactions.fire(app, cfg) {
+            if screen.is_some() {
+                // a missed notification is no reason to stop the timer
+                let _ = cfg
+                    .notifications
+                    .write(&mut io::stdout(), transition, &app.timer);
+            }
+        }
+        if let (Some((terminal, heading)), Some(title)) = (screen.as_mut(), title.as_mut()) {
+            title.set(&Status::of(&app.timer));
+            terminal.draw(|mut f| render::draw(&mut f, app, cfg, heading))?;
+        }
+        Ok(())
+    })?;
+    Ok(())
+}
+
+/// What reacts to the transitions of the timer besides the terminal.
+pub struct Reactions {
+    tx: mpsc::Sender<Event>,
+    // none without a session bus
+    desktop: Option<Desktop>,
+    webhooks: Option<Webhooks>,
+    // a demo runs no hooks and leaves the metrics and the status file alone
+    quiet: bool,
+    // what the textfile was last written with, the counts only change with
+    // transitions but the remaining time with every tick
+    sample: Option<Sample>,
+}
+
+impl Reactions {
+    pub fn new(cfg: &Cfg, events: &Events) -> Result<Reactions, failure::Error> {
+        if let Some(address) = &cfg.metrics.listen {
+            metrics::serve(address, cfg.db_path.clone(), events.sender())?;
+        }
+        Ok(Reactions {
+            tx: events.sender(),
+            desktop: if cfg.notifications.desktop {
+                Desktop::connect(events.sender())
+            } else {
+                None
+            },
+            webhooks: Webhooks::start(
+                &cfg.webhooks,
+                webhook::queue_path(&cfg.db_path),
+                cfg.webhook_retry,
+            ),
+            quiet: false,
+            sample: None,
+        })
+    }
+
+    /// Only the terminal reacts, for demos.
+    pub fn quiet(events: &Events) -> Reactions {
+        Reactions {
+            tx: events.sender(),
+            desktop: None,
+            webhooks: None,
+            quiet: true,
+            sample: None,
+        }
+    }
+
+    /// Runs the hooks of the transitions the last event caused, sends their
+    /// notifications and webhooks, updates the metrics and the status file and
+    /// returns them.
+    pub fn fire(&mut self, app: &mut App, cfg: &Cfg) -> Vec<Transition> {
+        let transitions = app.timer.take_transitions();
+        for &(transition, length) in &transitions {
+            app.hook_error = None;
+            app.integration_error = None;
+            if !self.quiet {
+                let tx = self.tx.clone();
+                cfg.hooks.run(&app.timer, transition, length, move |error| {
+                    let _ = tx.send(Event::HookFailed(error));
+                });
+            }
+            if let Some(desktop) = &self.desktop {
+                desktop.notify(transition, &app.timer);
+            }
+        }
+        for pom in app.take_finished() {
+            if let Some(webhooks) = &self.webhooks {
+                if let Err(e) = webhooks.send(&json!(pom)) {
+                    app.integration_error = Some(format!("webhooks: {}", e));
+                }
+            }
+        }
+        if !self.quiet {
+            if let Some(path) = &cfg.metrics.textfile {
+                if let Err(e) = self.write_textfile(path, app, cfg, !transitions.is_empty()) {
+                    app.integration_error = Some(format!("metrics: {}", e));
+                }
+            }
+            // the status is a convenience, the timer keeps running without it
+            let _ = Status::of(&app.timer).write();
+        }
+        transitions
+            .into_iter()
+            .map(|(transition, _)| transition)
+            .collect()
+    }
+
+    fn write_textfile(
+        &mut self,
+        path: &Path,
+        app: &App,
+        cfg: &Cfg,
+        transitioned: bool,
+    ) -> Result<(), failure::Error> {
+        let status = Status::of(&app.timer);
+        let sample = match self.sample.take() {
+            Some(sample) if !transitioned => Sample { status, ..sample },
+            _ => Sample::gather(&cfg.conn, status)?,
+        };
+        metrics::write_textfile(path, &sample.render())?;
+        self.sample = Some(sample);
+        Ok(())
+    }
+}
+
+/// Shows the running timer and sends it the keys typed, any number of
+/// terminals can be attached at the same time. Read-only clients only watch.
+/// Leaving one never stops the timer, that takes `pomodorust ctl stop`.
+pub fn attach(cfg: &mut Cfg, read_only: bool) -> Result<(), failure::Error> {
+    let mut client = Client::connect()?;
+    let mut events = Events::new();
+    let mut terminal = ui::terminal()?;
+    let result = attached(&mut client, &mut events, &mut terminal, cfg, read_only);
+    ui::restore(&mut terminal)?;
+    result
+}
+
+fn attached(
+    client: &mut Client,
+    events: &mut Events,
+    terminal: &mut ui::Tui,
+    cfg: &mut Cfg,
+    read_only: bool,
+) -> Result<(), failure::Error> {
+    let title = if read_only {
+        format!("read-only, '{}' detaches", QUIT_KEY)
+    } else {
+        format!(
+            "attached, '{}' detaches, `pomodorust ctl stop` stops the timer",
+            QUIT_KEY
+        )
+    };
+    let mut state = None;
+    let mut window_title = ui::Title::new(&cfg.title, cfg.notifications.urgent);
+    loop {
+        let snapshot = client.snapshot()?;
+        // the timer only tells its state, not what happened in between
+        let changed =
+            state.and_then(|old| notify::transition_between(old, snapshot.app.timer.state));
+        if let Some(transition) = changed {
+            let _ = cfg
+                .notifications
+                .write(&mut io::stdout(), transition, &snapshot.app.timer);
+        }
+        state = Some(snapshot.app.timer.state);
+        window_title.set(&Status::of(&snapshot.app.timer));
+        // drawn with the durations of the timer, not of our config
+        cfg.working = snapshot.working;
+        cfg.short_break = snapshot.short_break;
+        cfg.long_break = snapshot.long_break;
+        terminal.draw(|mut f| render::draw(&mut f, &snapshot.app, cfg, &title))?;
+
+        let key = match events.next()? {
+            Event::Input(key) => key,
+            _ => continue,
+        };
+        // an open form takes every key
+        let typing = snapshot.app.form().is_some();
+        match key {
+            Key::Char(QUIT_KEY) if read_only || !typing => return Ok(()),
+            _ if read_only => {}
+            _ => {
+                if let Some(name) = control::key_name(key) {
+                    client.request(&format!("key {}", name))?;
+                }
+            }
+        }
+    }
+}
@@ -1,115 +1,113 @@
-use failure;
+mod commands;
+mod daemon;
+mod simulate;
+mod ui;
+
+use clap::ArgMatches;
+use failure::{bail, format_err};
 use pomodorust::config::Cfg;
-use pomodorust::database::{create_table, HEADER};
-use pomodorust::events::{Event, Events};
+use pomodorust::control;
+use pomodorust::database::create_table;
+use pomodorust::events::Events;
+use pomodorust::http;
+use pomodorust::lock::Lock;
 use pomodorust::state::App;
-use std::io;
-use termion::raw::IntoRawMode;
-use tui::backend::TermionBackend;
-use tui::layout::{
-    Constraint::{Length, Min, Percentage},
-    Direction::{Horizontal, Vertical},
-    Layout,
-};
-use tui::style::{Color, Modifier, Style};
-use tui::widgets::{Block, Borders, Row, Table, Tabs, Widget};
-use tui::Terminal;
+use pomodorust::status::Status;
+use std::io::{self, Write};
 
 fn main() -> Result<(), failure::Error> {
-    let cfg = Cfg::from_opts()?;
+    let (mut cfg, matches) = Cfg::from_opts()?;
     create_table(&cfg.conn)?;
+    match matches.subcommand() {
+        ("daemon", Some(args)) => daemon::daemon(&cfg, args),
+        ("attach", Some(args)) => daemon::attach(&mut cfg, args.is_present("read-only")),
+        ("status", Some(args)) => commands::status(args),
+        ("ctl", Some(args)) => commands::ctl(args),
+        ("stats", Some(args)) => commands::stats(&cfg, args),
+        ("log", Some(args)) => commands::log(&cfg, args),
+        ("edit", Some(args)) => commands::edit(&cfg, args),
+        ("delete", Some(args)) => commands::delete(&cfg, args),
+        ("restore", Some(args)) => commands::restore(&cfg, args),
+        ("purge", Some(_)) => commands::purge(&cfg),
+        ("report", Some(args)) => commands::report(&cfg, args),
+        ("import", Some(args)) => commands::import(&cfg, args),
+        ("export", Some(args)) => commands::export(&cfg, args),
+        ("start", Some(args)) => match (count_arg(args, "simulate")?, count_arg(args, "speed")?) {
+            (Some(cycles), speed) => simulate::simulate(&mut cfg, cycles, speed),
+            (None, Some(speed)) => simulate::demo(&mut cfg, speed),
+            (None, None) => start(&mut cfg, args.is_present("force"), http::Address::of(args)),
+        },
+        _ => start(&mut cfg, false, None),
+    }
+}
 
-    let events = Events::new();
-    let stdout = io::stdout().into_raw_mode()?;
-    let backend = TermionBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
-    let mut app = App::new(&cfg);
-    terminal.clear()?;
-    terminal.hide_cursor()?;
-    let select_style = Style::default()
-        .bg(Color::Yellow)
-        .fg(Color::Black)
-        .modifier(Modifier::BOLD);
-
-    loop {
-        terminal.draw(|mut f| {
-            let size = f.size();
-            Block::default().render(&mut f, size);
-
-            let chunks = Layout::default()
-                .direction(Vertical)
-                .constraints([Length(3), Min(0)].as_ref())
-                .split(size);
-
-            Tabs::default()
-                .block(Block::default().borders(Borders::ALL))
-                .titles(&app.tabs())
-                .select(app.selected_tab)
-                .highlight_style(select_style)
-                .render(&mut f, chunks[0]);
-            match app.selected_tab {
-                0 => {
-                    let chunks_ = Layout::default()
-                        .direction(Vertical)
-                        .margin(0)
-                        .constraints([Percentage(75), Percentage(25)].as_ref())
-                        .split(chunks[1]);
-
-                    Block::default()
-                        .borders(Borders::ALL)
-                        .render(&mut f, chunks_[0]);
-                    {
-                        let chunks__ = Layout::default()
-                            .direction(Horizontal)
-                            .margin(1)
-                            .constraints([Percentage(50), Percentage(50)].as_ref())
-                            .split(chunks_[0]);
-
-                        app.paragraph(&mut f, chunks__[0]);
-                        cfg.paragraph(&mut f, chunks__[1]);
-                    }
-                    {
-                        app.gauge(&cfg, &mut f, chunks_[1]);
-                    }
-                }
-                1 => {
-                    let rows = app.pomodoros.iter().enumerate().map(|(i,pom)| {
-                        let row = vec![
-                                format!("{}", pom.id),
-                                format!("{}", pom.created_at),
-                                format!("{}", pom.duration),
-                                format!("{}", pom.ticket_id.to_owned().unwrap_or_default()),
-                                format!("{}", pom.note.to_owned().unwrap_or_default()),
-                            ];
-                        if Some(i) == app.selected_pomodoro {
-                            Row::StyledData(row.into_iter(), select_style)
-                        } else {
-                            Row::Data(row.into_iter())
-                        }
-                    });
+fn start(cfg: &mut Cfg, force: bool, http: Option<http::Address>) -> Result<(), failure::Error> {
+    let _lock = match Lock::acquire(&cfg.db_path, force) {
+        Ok(lock) => lock,
+        Err(e) => return occupied(cfg, e, http),
+    };
+    let mut events = Events::new();
+    if let Some(address) = http {
+        http::serve(address, cfg.db_path.clone(), events.sender())?;
+    }
+    // without the socket the timer can still be used from the terminal
+    let listening = control::listen(events.sender()).is_ok();
+    let mut terminal = ui::terminal()?;
+    let mut app = App::new(cfg);
+    let mut reactions = daemon::Reactions::new(cfg, &events)?;
+    let result = daemon::run_timer(
+        &mut app,
+        cfg,
+        &mut events,
+        &mut reactions,
+        Some((&mut terminal, "")),
+    );
+    let _ = Status::remove();
+    if listening {
+        control::unlink();
+    }
+    ui::restore(&mut terminal)?;
+    result
+}
 
-                    let rects = Layout::default()
-                        .constraints([Percentage(100)].as_ref())
-                        .split(chunks[1]);
-                    Table::new(HEADER.into_iter(), rows)
-                        .block(Block::default().borders(Borders::ALL))
-                        .widths(&[Length(05), Length(30), Length(30), Min(50), Min(50)])
-                        .render(&mut f, rects[0]);
-                }
-                _ => {}
-            }
-        })?;
+// a second timer on the same database would record every pomodoro twice
+fn occupied(
+    cfg: &mut Cfg,
+    error: failure::Error,
+    http: Option<http::Address>,
+) -> Result<(), failure::Error> {
+    if !termion::is_tty(&io::stdin()) {
+        bail!(
+            "{} `pomodorust attach` shows it, `pomodorust attach --read-only` only \
+             watches it and `pomodorust start --force` starts a second timer anyway.",
+            error
+        );
+    }
+    print!(
+        "{}\n[a]ttach to it, [r]ead-only view, [f]orce a second timer or [q]uit? ",
+        error
+    );
+    io::stdout().flush()?;
+    let mut choice = String::new();
+    io::stdin().read_line(&mut choice)?;
+    match choice.trim() {
+        "a" => daemon::attach(cfg, false),
+        "r" => daemon::attach(cfg, true),
+        "f" => start(cfg, true, http),
+        _ => Ok(()),
+    }
+}
 
-        match events.next()? {
-            Event::Input(key) => {
-                if app.key_handler(key) {
-                    break;
-                }
-            }
-            Event::Tick(duration) => app.tick(&cfg, duration)?,
-        }
+fn count_arg(args: &ArgMatches, name: &str) -> Result<Option<u32>, failure::Error> {
+    match args.value_of(name) {
+        Some(text) => match text.parse() {
+            Ok(count) if count > 0 => Ok(Some(count)),
+            _ => Err(format_err!(
+                "--{} takes a positive number, not '{}'.",
+                name,
+                text
+            )),
+        },
+        None => Ok(None),
     }
-    terminal.clear()?;
-    terminal.show_cursor()?;
-    Ok(())
 }
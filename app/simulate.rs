@@ -0,0 +1,119 @@
+//! The timer at a faster pace, to show it off or to see how a configuration
+//! plays out over a day. Both record into a database in memory and leave the
+//! lock, the control socket, hooks and webhooks alone.
+
+use crate::daemon::{self, Reactions};
+use crate::ui;
+use pomodorust::clock::{Accelerated, Clock, FakeClock};
+use pomodorust::config::Cfg;
+use pomodorust::events::{Event, EventSource, Events, Script, TICK};
+use pomodorust::state::{run_timer, App};
+use std::cell::Cell;
+use std::cmp::max;
+use std::rc::Rc;
+use std::sync::mpsc;
+use std::time::Duration;
+
+const FRAMES_PER_SEC: u32 = 20;
+
+/// The terminal interface with `speed` seconds passing every second.
+pub fn demo(cfg: &mut Cfg, speed: u32) -> Result<(), failure::Error> {
+    cfg.use_memory_db()?;
+    // redrawing for every second that passes would fall behind a fast clock
+    let tick = TICK * max(1, speed / FRAMES_PER_SEC);
+    let mut events = Events::with_clock(Accelerated::new(speed), tick, true);
+    let mut terminal = ui::terminal()?;
+    let mut app = App::new(cfg);
+    let mut reactions = Reactions::quiet(&events);
+    let heading = format!("demo at {}x", speed);
+    let result = daemon::run_timer(
+        &mut app,
+        cfg,
+        &mut events,
+        &mut reactions,
+        Some((&mut terminal, &heading)),
+    );
+    ui::restore(&mut terminal)?;
+    result
+}
+
+/// Runs the cycles of a pomodoro and its break without a terminal, printing
+/// the transitions and the pomodoros recorded. At full speed unless a
+/// `speed` is given.
+pub fn simulate(cfg: &mut Cfg, cycles: u32, speed: Option<u32>) -> Result<(), failure::Error> {
+    cfg.use_memory_db()?;
+    match speed {
+        Some(speed) => run(cfg, cycles, Accelerated::new(speed)),
+        None => run(cfg, cycles, FakeClock::default()),
+    }
+}
+
+fn run<C: Clock>(cfg: &Cfg, cycles: u32, clock: C) -> Result<(), failure::Error> {
+    let mut script = Script::new(clock);
+    // the database is empty, every fourth pomodoro gets the long break
+    for cycle in 1..=cycles {
+        let pause = if cycle % 4 == 0 {
+            cfg.long_break
+        } else {
+            cfg.short_break
+        };
+        script = script.wait(cfg.working + pause);
+    }
+    let elapsed = Rc::new(Cell::new(Duration::from_secs(0)));
+    let mut events = Timed {
+        events: script,
+        elapsed: elapsed.clone(),
+    };
+    let mut app = App::new(cfg);
+    let mut recorded = 0;
+    run_timer(&mut app, cfg, &mut events, |app| {
+        let at = clock_time(elapsed.get());
+        for (transition, length) in app.timer.take_transitions() {
+            println!(
+                "{} {:<16} {} min",
+                at,
+                transition.name(),
+                length.as_secs() / 60
+            );
+        }
+        for pom in app.take_finished() {
+            println!(
+                "{} inserted statistic {}: {} min, ticket {}",
+                at,
+                pom.id,
+                pom.duration,
+                pom.ticket_id.as_deref().unwrap_or("none")
+            );
+            recorded += 1;
+        }
+        Ok(())
+    })?;
+    println!(
+        "{} pomodoros in {} simulated",
+        recorded,
+        clock_time(elapsed.get())
+    );
+    Ok(())
+}
+
+// adds up the time that passes, shared with the one printing it
+struct Timed<S> {
+    events: S,
+    elapsed: Rc<Cell<Duration>>,
+}
+
+impl<S: EventSource> EventSource for Timed<S> {
+    fn next(&mut self) -> Result<Event, mpsc::RecvError> {
+        let event = self.events.next()?;
+        if let Event::Tick(duration) = event {
+            self.elapsed.set(self.elapsed.get() + duration);
+        }
+        Ok(event)
+    }
+}
+
+// hours and minutes since the simulation started
+fn clock_time(elapsed: Duration) -> String {
+    let mins = elapsed.as_secs() / 60;
+    format!("{:02}:{:02}", mins / 60, mins % 60)
+}
@@ -0,0 +1,70 @@
+use pomodorust::status::Status;
+use std::io::{self, Stdout, Write};
+use termion::raw::{IntoRawMode, RawTerminal};
+use tui::backend::TermionBackend;
+use tui::Terminal;
+
+/// Shows the timer in the title of the terminal window or tab, the title it
+/// had before comes back when dropped, as does the urgency of the bell.
+pub struct Title {
+    format: String,
+    shown: String,
+    urgent: bool,
+}
+
+impl Title {
+    pub fn new(format: &str, urgent: bool) -> Title {
+        if !format.is_empty() {
+            // xterm's title stack, terminals without it ignore this
+            print!("\x1b[22;0t");
+        }
+        Title {
+            format: String::from(format),
+            shown: String::new(),
+            urgent,
+        }
+    }
+
+    pub fn set(&mut self, status: &Status) {
+        let title: String = status
+            .format(&self.format)
+            .chars()
+            .filter(|c| !c.is_control())
+            .collect();
+        if self.format.is_empty() || title == self.shown {
+            return;
+        }
+        print!("\x1b]0;{}\x07", title);
+        let _ = io::stdout().flush();
+        self.shown = title;
+    }
+}
+
+impl Drop for Title {
+    fn drop(&mut self) {
+        if !self.format.is_empty() {
+            print!("\x1b[23;0t");
+        }
+        if self.urgent {
+            // notifications turned it on, it would outlive us otherwise
+            print!("\x1b[?1042l");
+        }
+        let _ = io::stdout().flush();
+    }
+}
+
+pub type Tui = Terminal<TermionBackend<RawTerminal<Stdout>>>;
+
+pub fn terminal() -> Result<Tui, failure::Error> {
+    let stdout = io::stdout().into_raw_mode()?;
+    let mut terminal = Terminal::new(TermionBackend::new(stdout))?;
+    terminal.clear()?;
+    terminal.hide_cursor()?;
+    Ok(terminal)
+}
+
+pub fn restore(terminal: &mut Tui) -> Result<(), failure::Error> {
+    terminal.clear()?;
+    terminal.show_cursor()?;
+    Ok(())
+}
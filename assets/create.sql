@@ -1,6 +1,16 @@
 CREATE TABLE IF NOT EXISTS statistics
 ( id INTEGER PRIMARY KEY AUTOINCREMENT
 , created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
-, jira_id TEXT NULL
+, duration INTEGER NOT NULL
+, ticket_id TEXT NULL
 , note TEXT NULL
+, manual BOOLEAN NOT NULL DEFAULT 0
+, deleted_at TIMESTAMP NULL
+);
+
+CREATE TABLE IF NOT EXISTS abandoned
+( id INTEGER PRIMARY KEY AUTOINCREMENT
+, created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+, duration INTEGER NOT NULL
+, ticket_id TEXT NULL
 )
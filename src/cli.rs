@@ -0,0 +1,401 @@
+use clap::{App, AppSettings, Arg, SubCommand};
+
+/// Command line of the `pomodorust` binary, without a subcommand it behaves
+/// like `start`.
+pub fn options() -> App<'static, 'static> {
+    App::new("PomodoRust")
+        .version("1.1.0")
+        .author("Martin Heuschober <epsilonhalbe@gmail.com>")
+        .about("commandline pomodoro thingy")
+        .setting(AppSettings::VersionlessSubcommands)
+        .arg(
+            Arg::with_name("config")
+                .short("c")
+                .long("config")
+                .value_name("FILE")
+                .help("Sets a custom config file like")
+                .takes_value(true)
+                .global(true),
+        )
+        .subcommand(
+            SubCommand::with_name("start")
+                .about("Runs the pomodoro timer (default)")
+                .arg(force_arg())
+                .arg(http_arg())
+                .arg(http_public_arg())
+                .arg(
+                    Arg::with_name("speed")
+                        .long("speed")
+                        .value_name("FACTOR")
+                        .help(
+                            "Runs a demo this many times faster, e.g. 60 for a minute per second, \
+                             recording into a database in memory",
+                        )
+                        .takes_value(true)
+                        .conflicts_with_all(&["force", "http"]),
+                )
+                .arg(
+                    Arg::with_name("simulate")
+                        .long("simulate")
+                        .value_name("CYCLES")
+                        .help(
+                            "Runs this many pomodoros and their breaks without a terminal \
+                             against a database in memory, printing what happens",
+                        )
+                        .takes_value(true)
+                        .conflicts_with_all(&["force", "http"]),
+                ),
+        )
+        .subcommand(daemon())
+        .subcommand(
+            SubCommand::with_name("attach")
+                .about("Shows the running timer in this terminal, 'q' leaves it running")
+                .arg(
+                    Arg::with_name("read-only")
+                        .short("r")
+                        .long("read-only")
+                        .help("Only watches the timer, keys are not sent to it"),
+                ),
+        )
+        .subcommand(status())
+        .subcommand(ctl())
+        .subcommand(stats())
+        .subcommand(log())
+        .subcommand(edit())
+        .subcommand(delete())
+        .subcommand(restore())
+        .subcommand(purge())
+        .subcommand(report())
+        .subcommand(import())
+        .subcommand(export())
+}
+
+fn daemon() -> App<'static, 'static> {
+    SubCommand::with_name("daemon")
+        .about("Runs the pomodoro timer in the background")
+        .arg(
+            Arg::with_name("foreground")
+                .long("foreground")
+                .help("Runs it in this process, e.g. under a service manager"),
+        )
+        .arg(force_arg())
+        .arg(http_arg())
+        .arg(http_public_arg())
+}
+
+fn status() -> App<'static, 'static> {
+    SubCommand::with_name("status")
+        .about("Prints the state of the running timer for status bars")
+        .arg(
+            Arg::with_name("format")
+                .short("f")
+                .long("format")
+                .value_name("FORMAT")
+                .help(
+                    "i3bar, waybar or a text with {state}, {remaining}, {count} and {ticket}, \
+                     defaults to '{state} {remaining} {count}'",
+                )
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("text")
+                .short("t")
+                .long("text")
+                .value_name("TEXT")
+                .help("Text with placeholders used by the i3bar and waybar formats")
+                .takes_value(true),
+        )
+}
+
+fn ctl() -> App<'static, 'static> {
+    SubCommand::with_name("ctl")
+        .about("Controls the running timer")
+        .arg(
+            Arg::with_name("command")
+                .value_name("COMMAND")
+                .possible_values(&[
+                    "pause",
+                    "resume",
+                    "skip",
+                    "abandon",
+                    "set-ticket",
+                    "status",
+                    "stop",
+                ])
+                .required(true),
+        )
+        .arg(
+            Arg::with_name("ticket")
+                .value_name("TICKET")
+                .help("Ticket for set-ticket, none clears it"),
+        )
+}
+
+fn stats() -> App<'static, 'static> {
+    SubCommand::with_name("stats")
+        .about("Counts pomodoros and their time per day, week, month or ticket")
+        .arg(since_arg().help("First day to count as YYYY-MM-DD"))
+        .arg(until_arg().help("Last day to count as YYYY-MM-DD"))
+        .arg(
+            Arg::with_name("ticket")
+                .short("t")
+                .long("ticket")
+                .value_name("GLOB")
+                .help("Only counts tickets matching GLOB, e.g. 'PROJ-*'")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("group-by")
+                .short("g")
+                .long("group-by")
+                .value_name("GROUP")
+                .possible_values(&["day", "week", "month", "ticket"])
+                .default_value("day"),
+        )
+        .arg(
+            Arg::with_name("format")
+                .short("f")
+                .long("format")
+                .value_name("FORMAT")
+                .possible_values(&["table", "json"])
+                .default_value("table"),
+        )
+}
+
+fn log() -> App<'static, 'static> {
+    SubCommand::with_name("log")
+        .about("Records a pomodoro done away from the timer")
+        .arg(
+            Arg::with_name("start")
+                .short("s")
+                .long("start")
+                .value_name("TIME")
+                .help(
+                    "When the pomodoro started in local time, e.g. '2020-01-02 14:30', defaults to just finished",
+                )
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("duration")
+                .short("d")
+                .long("duration")
+                .value_name("MINUTES")
+                .help("Defaults to the configured working duration")
+                .takes_value(true),
+        )
+        .arg(ticket_arg())
+        .arg(note_arg())
+}
+
+fn edit() -> App<'static, 'static> {
+    SubCommand::with_name("edit")
+        .about("Changes ticket or note of a recorded pomodoro")
+        .arg(id_arg())
+        .arg(ticket_arg())
+        .arg(note_arg())
+}
+
+fn delete() -> App<'static, 'static> {
+    SubCommand::with_name("delete")
+        .about("Deletes a recorded pomodoro, it is kept until purged")
+        .arg(id_arg())
+}
+
+fn restore() -> App<'static, 'static> {
+    SubCommand::with_name("restore")
+        .about("Brings back a deleted pomodoro")
+        .arg(id_arg())
+}
+
+fn purge() -> App<'static, 'static> {
+    SubCommand::with_name("purge").about("Removes deleted pomodoros for good")
+}
+
+fn report() -> App<'static, 'static> {
+    SubCommand::with_name("report")
+        .about("Writes a markdown report of a week")
+        .arg(
+            Arg::with_name("week")
+                .short("w")
+                .long("week")
+                .help("Reports the week from monday to sunday (default)"),
+        )
+        .arg(
+            Arg::with_name("date")
+                .short("d")
+                .long("date")
+                .value_name("DATE")
+                .help("Reports the week containing DATE instead of the current one")
+                .takes_value(true),
+        )
+        .arg(
+            since_arg()
+                .help("First day to report as YYYY-MM-DD instead of a week")
+                .requires("until"),
+        )
+        .arg(until_arg().help("Last day to report as YYYY-MM-DD"))
+        .arg(
+            Arg::with_name("template")
+                .short("t")
+                .long("template")
+                .value_name("FILE")
+                .help("Markdown with {{placeholders}}, see assets/report.md")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("output")
+                .short("o")
+                .long("output")
+                .value_name("FILE")
+                .help("Writes to FILE instead of stdout")
+                .takes_value(true),
+        )
+}
+
+fn import() -> App<'static, 'static> {
+    SubCommand::with_name("import")
+        .about("Imports pomodoros from a csv file")
+        .arg(
+            Arg::with_name("file")
+                .value_name("FILE")
+                .help("csv file with a header row, or the json of `timew export`")
+                .required(true),
+        )
+        .arg(
+            Arg::with_name("format")
+                .short("f")
+                .long("format")
+                .value_name("FORMAT")
+                .possible_values(&["csv", "timew"])
+                .default_value("csv"),
+        )
+        .arg(
+            Arg::with_name("columns")
+                .short("m")
+                .long("columns")
+                .value_name("MAPPING")
+                .help("Maps fields to csv columns, e.g. created_at=Start,duration=Minutes (csv)")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("dry-run")
+                .short("n")
+                .long("dry-run")
+                .help("Shows what would be inserted without writing anything"),
+        )
+}
+
+fn export() -> App<'static, 'static> {
+    SubCommand::with_name("export")
+        .about("Exports recorded pomodoros of a date range")
+        .arg(
+            Arg::with_name("format")
+                .short("f")
+                .long("format")
+                .value_name("FORMAT")
+                .possible_values(&["ics", "jira", "org", "timew"])
+                .default_value("ics"),
+        )
+        .arg(since_arg().help("First day to export as YYYY-MM-DD, defaults to today"))
+        .arg(until_arg().help("Last day to export as YYYY-MM-DD, defaults to today"))
+        .arg(
+            Arg::with_name("breaks")
+                .short("b")
+                .long("breaks")
+                .help("Also exports the breaks between pomodoros"),
+        )
+        .arg(
+            Arg::with_name("output")
+                .short("o")
+                .long("output")
+                .value_name("FILE")
+                .help("Writes to FILE instead of stdout")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("round")
+                .long("round")
+                .value_name("MINUTES")
+                .help("Rounds the time logged per ticket and day (jira)")
+                .default_value("15"),
+        )
+        .arg(
+            Arg::with_name("rounding")
+                .long("rounding")
+                .value_name("MODE")
+                .help("How to round logged time (jira)")
+                .possible_values(&["up", "down", "nearest"])
+                .default_value("nearest"),
+        )
+        .arg(
+            Arg::with_name("author")
+                .long("author")
+                .value_name("USER")
+                .help("Jira user the work is logged for, defaults to $USER (jira)")
+                .takes_value(true),
+        )
+}
+
+fn force_arg() -> Arg<'static, 'static> {
+    Arg::with_name("force")
+        .long("force")
+        .help("Starts even if another timer is running on the same database")
+}
+
+fn http_arg() -> Arg<'static, 'static> {
+    Arg::with_name("http")
+        .long("http")
+        .value_name("ADDRESS")
+        .help("Serves a JSON API for integrations, e.g. on 127.0.0.1:8099")
+        .takes_value(true)
+}
+
+fn http_public_arg() -> Arg<'static, 'static> {
+    Arg::with_name("http-public")
+        .long("http-public")
+        .requires("http")
+        .help(
+            "Serves the API on an address other than loopback, where anyone can control the timer",
+        )
+}
+
+fn since_arg() -> Arg<'static, 'static> {
+    Arg::with_name("since")
+        .short("s")
+        .long("since")
+        .value_name("DATE")
+        .takes_value(true)
+}
+
+fn until_arg() -> Arg<'static, 'static> {
+    Arg::with_name("until")
+        .short("u")
+        .long("until")
+        .value_name("DATE")
+        .takes_value(true)
+}
+
+fn id_arg() -> Arg<'static, 'static> {
+    Arg::with_name("id")
+        .value_name("ID")
+        .help("ID as shown in the statistics")
+        .required(true)
+}
+
+fn ticket_arg() -> Arg<'static, 'static> {
+    Arg::with_name("ticket")
+        .short("t")
+        .long("ticket")
+        .value_name("TICKET")
+        .help("Ticket the pomodoro was spent on, empty to clear it")
+        .takes_value(true)
+}
+
+fn note_arg() -> Arg<'static, 'static> {
+    Arg::with_name("note")
+        .short("n")
+        .long("note")
+        .value_name("NOTE")
+        .help("Note about the pomodoro, empty to clear it")
+        .takes_value(true)
+}
@@ -0,0 +1,83 @@
+//! Where the timer gets its time from, so that tests and simulations need not
+//! wait for the wall clock.
+
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub trait Clock: Send {
+    /// Time since the clock started, only the difference of two calls counts.
+    fn now(&self) -> Duration;
+    fn sleep(&self, duration: Duration);
+}
+
+/// The monotonic clock of the system.
+pub struct SystemClock {
+    start: Instant,
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        SystemClock {
+            start: Instant::now(),
+        }
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        thread::sleep(duration)
+    }
+}
+
+/// The system clock sped up, `speed` seconds pass for every real one.
+pub struct Accelerated {
+    clock: SystemClock,
+    speed: u32,
+}
+
+impl Accelerated {
+    pub fn new(speed: u32) -> Accelerated {
+        Accelerated {
+            clock: SystemClock::default(),
+            speed,
+        }
+    }
+}
+
+impl Clock for Accelerated {
+    fn now(&self) -> Duration {
+        self.clock.now() * self.speed
+    }
+
+    fn sleep(&self, duration: Duration) {
+        self.clock.sleep(duration / self.speed)
+    }
+}
+
+/// A clock that only moves when told to or slept on, which takes no time.
+/// Clones share their time.
+#[derive(Clone, Default)]
+pub struct FakeClock {
+    now: Arc<Mutex<Duration>>,
+}
+
+impl FakeClock {
+    pub fn advance(&self, duration: Duration) {
+        *self.now.lock().unwrap() += duration;
+    }
+}
+
+impl Clock for FakeClock {
+    fn now(&self) -> Duration {
+        *self.now.lock().unwrap()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        self.advance(duration)
+    }
+}
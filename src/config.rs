@@ -1,17 +1,31 @@
-use clap::{App, Arg};
+use crate::cli;
+#[cfg(feature = "sqlite")]
+use crate::database::create_table;
+use crate::hooks::Hooks;
+use crate::metrics::Metrics;
+use crate::notify::Notifications;
+use crate::timer::Durations;
+use clap::ArgMatches;
 use config::{Config, File, FileFormat};
-use diesel::prelude::Connection;
-use diesel::sqlite::SqliteConnection;
+#[cfg(feature = "sqlite")]
+use diesel::{prelude::Connection, sqlite::SqliteConnection};
 use failure;
 use serde::{Deserialize, Serialize};
-use std::env::var_os;
+use std::collections::HashMap;
+use std::env::{temp_dir, var_os};
+use std::fs::{self, DirBuilder};
+use std::io;
+use std::os::unix::fs::{DirBuilderExt, MetadataExt, PermissionsExt};
 use std::path::PathBuf;
 use std::time::Duration;
-use tui::backend::Backend;
-use tui::layout::Rect;
-use tui::style::{Color, Style};
-use tui::terminal::Frame;
-use tui::widgets::{Block, Paragraph, Text, Widget};
+#[cfg(feature = "tui")]
+use tui::{
+    backend::Backend,
+    layout::Rect,
+    style::{Color, Style},
+    terminal::Frame,
+    widgets::{Block, Paragraph, Text, Widget},
+};
 
 #[derive(Deserialize, Serialize, Debug)]
 pub struct CfgDTO {
@@ -19,15 +33,83 @@ pub struct CfgDTO {
     pub short_break_mins: u64,
     pub long_break_mins: u64,
     pub db_path: PathBuf,
+    // transition names to shell commands
+    #[serde(default)]
+    pub hooks: HashMap<String, String>,
+    #[serde(default = "default_hook_timeout")]
+    pub hook_timeout_secs: u64,
+    #[serde(default)]
+    pub notifications: Notifications,
+    // of the terminal, with the placeholders of `status`, empty leaves it alone
+    #[serde(default = "default_title")]
+    pub title: String,
+    // completed pomodoros are POSTed to these
+    #[serde(default)]
+    pub webhooks: Vec<String>,
+    #[serde(default = "default_webhook_retry")]
+    pub webhook_retry_secs: u64,
+    #[serde(default)]
+    pub metrics: Metrics,
+}
+
+fn default_hook_timeout() -> u64 {
+    10
+}
+
+fn default_webhook_retry() -> u64 {
+    30
+}
+
+fn default_title() -> String {
+    String::from("🍅 {remaining} {ticket}")
 }
 
 pub const PAUSE_KEY: char = 'p';
+pub const ABANDON_KEY: char = 'x';
+// only detaches when attached, the timer keeps running
 pub const QUIT_KEY: char = 'q';
 
+/// Directory for files of the running timer, created on first use. The
+/// control socket in it takes commands, so it has to be the user's alone.
+pub fn runtime_dir() -> io::Result<PathBuf> {
+    let dir = match var_os("XDG_RUNTIME_DIR") {
+        Some(dir) => PathBuf::from(dir).join("pomodorust"),
+        None => temp_dir().join(format!(
+            "pomodorust-{}",
+            var_os("USER").unwrap_or_default().to_string_lossy()
+        )),
+    };
+    if let Err(e) = DirBuilder::new().recursive(true).mode(0o700).create(&dir) {
+        if e.kind() != io::ErrorKind::AlreadyExists {
+            return Err(e);
+        }
+    }
+    // anyone can create it first in the temporary directory
+    let meta = fs::symlink_metadata(&dir)?;
+    if !meta.is_dir() || meta.uid() != unsafe { libc::getuid() } {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("{} belongs to another user", dir.display()),
+        ));
+    }
+    if meta.mode() & 0o077 != 0 {
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o700))?;
+    }
+    Ok(dir)
+}
+
 pub struct Cfg {
     pub working: Duration,
     pub short_break: Duration,
     pub long_break: Duration,
+    pub db_path: PathBuf,
+    pub hooks: Hooks,
+    pub notifications: Notifications,
+    pub title: String,
+    pub webhooks: Vec<String>,
+    pub webhook_retry: Duration,
+    pub metrics: Metrics,
+    #[cfg(feature = "sqlite")]
     pub conn: SqliteConnection,
 }
 
@@ -38,48 +120,53 @@ impl Default for CfgDTO {
             short_break_mins: 5,
             long_break_mins: 10,
             db_path: PathBuf::from("pomodorust.db"),
+            hooks: HashMap::new(),
+            hook_timeout_secs: default_hook_timeout(),
+            notifications: Notifications::default(),
+            title: default_title(),
+            webhooks: Vec::new(),
+            webhook_retry_secs: default_webhook_retry(),
+            metrics: Metrics::default(),
         }
     }
 }
 
 impl CfgDTO {
     fn from(&self) -> Result<Cfg, failure::Error> {
-        let conn = SqliteConnection::establish(self.db_path.to_str().unwrap())?;
         Ok(Cfg {
             working: Duration::from_secs(self.working_mins * 60),
             short_break: Duration::from_secs(self.short_break_mins * 60),
             long_break: Duration::from_secs(self.long_break_mins * 60),
-            conn: conn,
+            db_path: self.db_path.clone(),
+            hooks: Hooks::new(&self.hooks, Duration::from_secs(self.hook_timeout_secs))?,
+            notifications: self.notifications,
+            title: self.title.clone(),
+            webhooks: self.webhooks.clone(),
+            webhook_retry: Duration::from_secs(self.webhook_retry_secs),
+            metrics: self.metrics.clone(),
+            #[cfg(feature = "sqlite")]
+            conn: SqliteConnection::establish(self.db_path.to_str().unwrap())?,
         })
     }
 }
 
 impl Cfg {
-    pub fn from_opts() -> Result<Cfg, failure::Error> {
+    pub fn from_opts() -> Result<(Cfg, ArgMatches<'static>), failure::Error> {
         let def_path = var_os("XDG_CONFIG_HOME")
             .map(PathBuf::from)
             .or(var_os("HOME").map(PathBuf::from).map(|x| x.join(".config")))
             .map(|s| s.join("pomodorust/config.yaml"));
 
-        let options: App = App::new("PomodoRust")
-            .version("1.1.0")
-            .author("Martin Heuschober <epsilonhalbe@gmail.com>")
-            .about("commandline pomodoro thingy")
-            .arg(
-                Arg::with_name("config")
-                    .short("c")
-                    .long("config")
-                    .value_name("FILE")
-                    .help("Sets a custom config file like")
-                    .takes_value(true),
-            );
-
+        let matches = cli::options().get_matches();
+        // `--config` is global, it may also be given after the subcommand
+        let config_path = matches.value_of_os("config").or_else(|| {
+            matches
+                .subcommand()
+                .1
+                .and_then(|args| args.value_of_os("config"))
+        });
         let mut cfg = Config::default();
-        let dto: CfgDTO = match options
-            .get_matches()
-            .value_of_os("config")
-            .map(PathBuf::from)
-        {
+        let dto: CfgDTO = match config_path.map(PathBuf::from) {
             Some(path) => {
                 if path.is_file() {
                     cfg.merge(File::from(path).format(FileFormat::Yaml))?;
@@ -101,9 +188,43 @@ impl Cfg {
                 }
             }
         };
-        dto.from()
+        Ok((dto.from()?, matches))
+    }
+
+    pub fn durations(&self) -> Durations {
+        Durations {
+            working: self.working,
+            short_break: self.short_break,
+            long_break: self.long_break,
+        }
+    }
+
+    /// Records into a database in memory, gone on exit, for demos.
+    #[cfg(feature = "sqlite")]
+    pub fn use_memory_db(&mut self) -> Result<(), failure::Error> {
+        self.db_path = PathBuf::from(":memory:");
+        self.conn = SqliteConnection::establish(":memory:")?;
+        create_table(&self.conn)?;
+        Ok(())
+    }
+
+    /// The defaults with other durations, recording into a database in
+    /// memory, for tests.
+    #[cfg(feature = "sqlite")]
+    pub fn in_memory(durations: Durations) -> Result<Cfg, failure::Error> {
+        let dto = CfgDTO {
+            db_path: PathBuf::from(":memory:"),
+            ..CfgDTO::default()
+        };
+        let mut cfg = dto.from()?;
+        cfg.use_memory_db()?;
+        cfg.working = durations.working;
+        cfg.short_break = durations.short_break;
+        cfg.long_break = durations.long_break;
+        Ok(cfg)
     }
 
+    #[cfg(feature = "tui")]
     pub fn paragraph<B>(&self, f: &mut Frame<B>, area: Rect)
     where
         B: Backend,
@@ -123,12 +244,13 @@ impl Cfg {
     }
 }
 
+#[cfg(feature = "tui")]
 fn time_with_label<'a>(time: Duration, label: String) -> Text<'a> {
     Text::raw(format!(
         "{}: {}:{:02}:{:02}\n",
         label,
         time.as_secs() / 3600,
-        time.as_secs() / 60,
+        time.as_secs() / 60 % 60,
         time.as_secs() % 60
     ))
 }
@@ -0,0 +1,187 @@
+use crate::config::runtime_dir;
+use crate::events::Event;
+use crate::state::App;
+use failure::{self, bail, format_err};
+use serde::Deserialize;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+use termion::event::Key;
+
+/// Commands a running timer accepts on its control socket, one per line.
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Pause,
+    Resume,
+    Skip,
+    Abandon,
+    SetTicket(Option<String>),
+    Status,
+    // used by attached clients
+    Key(Key),
+    Snapshot,
+    Stop,
+}
+
+impl FromStr for Command {
+    type Err = failure::Error;
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let mut words = line.trim().splitn(2, ' ');
+        let command = words.next().unwrap_or_default();
+        let argument = words.next().map(str::trim).filter(|a| !a.is_empty());
+        match command {
+            "pause" => Ok(Command::Pause),
+            "resume" => Ok(Command::Resume),
+            "skip" => Ok(Command::Skip),
+            "abandon" => Ok(Command::Abandon),
+            "set-ticket" => Ok(Command::SetTicket(argument.map(String::from))),
+            "status" => Ok(Command::Status),
+            "key" => Ok(Command::Key(parse_key(argument.unwrap_or_default())?)),
+            "snapshot" => Ok(Command::Snapshot),
+            "stop" => Ok(Command::Stop),
+            _ => bail!("unknown command '{}'", command),
+        }
+    }
+}
+
+/// What an attached client needs to draw the timer, the answer to `snapshot`.
+#[derive(Deserialize)]
+pub struct Snapshot {
+    pub app: App,
+    pub working: Duration,
+    pub short_break: Duration,
+    pub long_break: Duration,
+}
+
+/// The argument of the `key` command, None for keys the timer ignores.
+pub fn key_name(key: Key) -> Option<String> {
+    match key {
+        Key::Char(c) => Some(format!("char {}", u32::from(c))),
+        Key::Up => Some(String::from("up")),
+        Key::Down => Some(String::from("down")),
+        Key::BackTab => Some(String::from("backtab")),
+        Key::Backspace => Some(String::from("backspace")),
+        Key::Esc => Some(String::from("esc")),
+        _ => None,
+    }
+}
+
+// characters are sent as their code so that space and newline survive
+fn parse_key(name: &str) -> Result<Key, failure::Error> {
+    let mut words = name.split_whitespace();
+    match (words.next(), words.next()) {
+        (Some("char"), Some(code)) => code
+            .parse()
+            .ok()
+            .and_then(std::char::from_u32)
+            .map(Key::Char)
+            .ok_or_else(|| format_err!("'{}' is not a character code", code)),
+        (Some("up"), None) => Ok(Key::Up),
+        (Some("down"), None) => Ok(Key::Down),
+        (Some("backtab"), None) => Ok(Key::BackTab),
+        (Some("backspace"), None) => Ok(Key::Backspace),
+        (Some("esc"), None) => Ok(Key::Esc),
+        _ => bail!("unknown key '{}'", name),
+    }
+}
+
+pub fn socket_path() -> io::Result<PathBuf> {
+    Ok(runtime_dir()?.join("control.sock"))
+}
+
+/// Accepts connections on the control socket and feeds their commands into
+/// the event loop, every command is answered with one line.
+pub fn listen(tx: mpsc::Sender<Event>) -> io::Result<thread::JoinHandle<()>> {
+    let path = socket_path()?;
+    if UnixStream::connect(&path).is_ok() {
+        return Err(io::Error::new(
+            io::ErrorKind::AddrInUse,
+            "another timer is listening on the control socket",
+        ));
+    }
+    // left behind by a timer that did not exit cleanly
+    let _ = fs::remove_file(&path);
+    let listener = UnixListener::bind(&path)?;
+    Ok(thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let tx = tx.clone();
+            thread::spawn(move || serve(stream, tx));
+        }
+    }))
+}
+
+pub fn unlink() {
+    if let Ok(path) = socket_path() {
+        let _ = fs::remove_file(path);
+    }
+}
+
+fn serve(stream: UnixStream, tx: mpsc::Sender<Event>) {
+    let mut writer = match stream.try_clone() {
+        Ok(writer) => writer,
+        Err(_) => return,
+    };
+    for line in BufReader::new(stream).lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(_) => return,
+        };
+        let answer = match line.parse::<Command>() {
+            Ok(command) => {
+                let (reply_tx, reply_rx) = mpsc::channel();
+                if tx.send(Event::Command(command, reply_tx)).is_err() {
+                    return;
+                }
+                reply_rx
+                    .recv()
+                    .unwrap_or_else(|_| String::from("error: timer stopped"))
+            }
+            Err(e) => format!("error: {}", e),
+        };
+        if writeln!(writer, "{}", answer).is_err() {
+            return;
+        }
+    }
+}
+
+/// A connection to the running timer that sends any number of commands.
+pub struct Client {
+    reader: BufReader<UnixStream>,
+    writer: UnixStream,
+}
+
+impl Client {
+    pub fn connect() -> Result<Client, failure::Error> {
+        let stream = match UnixStream::connect(socket_path()?) {
+            Ok(stream) => stream,
+            Err(_) => bail!("No timer is running."),
+        };
+        Ok(Client {
+            writer: stream.try_clone()?,
+            reader: BufReader::new(stream),
+        })
+    }
+
+    pub fn request(&mut self, line: &str) -> Result<String, failure::Error> {
+        writeln!(self.writer, "{}", line)?;
+        let mut answer = String::new();
+        if self.reader.read_line(&mut answer)? == 0 {
+            bail!("The timer stopped.");
+        }
+        Ok(answer.trim_end().to_string())
+    }
+
+    pub fn snapshot(&mut self) -> Result<Snapshot, failure::Error> {
+        Ok(serde_json::from_str(&self.request("snapshot")?)?)
+    }
+}
+
+/// Sends one command line to the running timer and returns its answer.
+pub fn send(line: &str) -> Result<String, failure::Error> {
+    Client::connect()?.request(line)
+}
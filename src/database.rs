@@ -1,50 +1,207 @@
 use crate::schema;
-use crate::schema::statistics;
-use chrono::NaiveDateTime;
+use crate::schema::{abandoned, statistics};
+use chrono::{Duration, NaiveDate, NaiveDateTime};
 use diesel::dsl::*;
 use diesel::insert_into;
 use diesel::prelude::*;
 use diesel::sql_query;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
 // use tui::widgets::{Row};
 
-#[derive(Insertable, PartialEq, Debug)]
+#[derive(Insertable, Serialize, PartialEq, Debug)]
 #[table_name = "statistics"]
 pub struct Statistic {
+    pub created_at: Option<NaiveDateTime>,
     pub duration: i64,
     pub ticket_id: Option<String>,
     pub note: Option<String>,
+    pub manual: bool,
 }
 
-#[derive(Queryable, PartialEq, Debug)]
+#[derive(Queryable, Serialize, Deserialize, PartialEq, Debug)]
 pub struct Pomodoro {
     pub id: i32,
     pub created_at: NaiveDateTime,
     pub duration: i64,
     pub ticket_id: Option<String>,
     pub note: Option<String>,
+    // entered by hand instead of recorded by the timer
+    pub manual: bool,
+    pub deleted_at: Option<NaiveDateTime>,
 }
 
 impl Pomodoro {
     pub fn as_row(&self) -> Vec<String> {
-        let ticket_id = self.ticket_id.clone().unwrap_or(String::new());
-        let note = self.note.clone().unwrap_or(String::new());
+        let ticket_id = self.ticket_id.clone().unwrap_or_default();
+        let note = self.note.clone().unwrap_or_default();
         vec![
             format!("{}", self.id),
             format!("{}", self.created_at),
             format!("{}", self.duration),
             ticket_id,
             note,
+            String::from(if self.manual { "yes" } else { "" }),
         ]
     }
 
+    // a pomodoro is recorded when it is finished
+    pub fn started_at(&self) -> NaiveDateTime {
+        self.created_at - Duration::minutes(self.duration)
+    }
+
+    pub fn finished_at(&self) -> NaiveDateTime {
+        self.created_at
+    }
+
     pub fn pomodoros_of(conn: &SqliteConnection, day: NaiveDateTime) -> QueryResult<Vec<Pomodoro>> {
         use schema::statistics::dsl::*;
         let sqltext = format!("date(created_at) = date('{}')", day);
-        statistics.filter(sql(&sqltext)).load::<Pomodoro>(conn)
+        statistics
+            .filter(deleted_at.is_null())
+            .filter(sql(&sqltext))
+            .load::<Pomodoro>(conn)
+    }
+
+    pub fn pomodoros_between(
+        conn: &SqliteConnection,
+        since: NaiveDate,
+        until: NaiveDate,
+    ) -> QueryResult<Vec<Pomodoro>> {
+        use schema::statistics::dsl::*;
+        let sqltext = format!(
+            "date(created_at) BETWEEN date('{}') AND date('{}')",
+            since, until
+        );
+        statistics
+            .filter(deleted_at.is_null())
+            .filter(sql(&sqltext))
+            .order(created_at)
+            .load::<Pomodoro>(conn)
     }
 }
 
-pub const HEADER: [&'static str; 5] = ["ID", "Created At", "Duration", "Ticket", "Note"];
+/// Time spent on a ticket during one day, as it is logged in Jira.
+#[derive(QueryableByName, PartialEq, Debug)]
+pub struct Worklog {
+    #[sql_type = "diesel::sql_types::Text"]
+    pub ticket_id: String,
+    #[sql_type = "diesel::sql_types::Date"]
+    pub day: NaiveDate,
+    #[sql_type = "diesel::sql_types::Timestamp"]
+    pub started_at: NaiveDateTime,
+    #[sql_type = "diesel::sql_types::BigInt"]
+    pub duration: i64,
+    #[sql_type = "diesel::sql_types::Nullable<diesel::sql_types::Text>"]
+    pub notes: Option<String>,
+}
+
+impl Worklog {
+    pub fn worklogs_between(
+        conn: &SqliteConnection,
+        since: NaiveDate,
+        until: NaiveDate,
+    ) -> QueryResult<Vec<Worklog>> {
+        use diesel::sql_types::Date;
+        sql_query(
+            "SELECT ticket_id
+                  , date(created_at) AS day
+                  , min(datetime(created_at, '-' || duration || ' minutes')) AS started_at
+                  , sum(duration) AS duration
+                  , group_concat(note, ' / ') AS notes
+            FROM statistics
+            WHERE ticket_id IS NOT NULL
+              AND deleted_at IS NULL
+              AND date(created_at) BETWEEN date(?) AND date(?)
+            GROUP BY ticket_id, date(created_at)
+            ORDER BY day, ticket_id",
+        )
+        .bind::<Date, _>(since)
+        .bind::<Date, _>(until)
+        .load(conn)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GroupBy {
+    Day,
+    Week,
+    Month,
+    Ticket,
+}
+
+impl FromStr for GroupBy {
+    type Err = failure::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "day" => Ok(GroupBy::Day),
+            "week" => Ok(GroupBy::Week),
+            "month" => Ok(GroupBy::Month),
+            "ticket" => Ok(GroupBy::Ticket),
+            _ => failure::bail!("Cannot group by '{}'.", s),
+        }
+    }
+}
+
+impl GroupBy {
+    // weeks are named after their monday
+    fn key(self) -> &'static str {
+        match self {
+            GroupBy::Day => "date(created_at)",
+            GroupBy::Week => "date(created_at, 'weekday 0', '-6 days')",
+            GroupBy::Month => "strftime('%Y-%m', created_at)",
+            GroupBy::Ticket => "ticket_id",
+        }
+    }
+}
+
+/// Restricts aggregations to a range of days and tickets matching a glob.
+#[derive(Debug, Default)]
+pub struct Filter {
+    pub since: Option<NaiveDate>,
+    pub until: Option<NaiveDate>,
+    pub ticket: Option<String>,
+}
+
+/// Number of pomodoros and their summed duration in minutes for one group.
+#[derive(QueryableByName, Serialize, PartialEq, Debug)]
+pub struct Aggregate {
+    #[sql_type = "diesel::sql_types::Nullable<diesel::sql_types::Text>"]
+    pub group: Option<String>,
+    #[sql_type = "diesel::sql_types::BigInt"]
+    pub count: i64,
+    #[sql_type = "diesel::sql_types::BigInt"]
+    pub duration: i64,
+}
+
+impl Aggregate {
+    pub fn aggregates(
+        conn: &SqliteConnection,
+        filter: &Filter,
+        group_by: GroupBy,
+    ) -> QueryResult<Vec<Aggregate>> {
+        use diesel::sql_types::{Date, Nullable, Text};
+        sql_query(format!(
+            "SELECT {} AS \"group\"
+                  , count(*) AS count
+                  , sum(duration) AS duration
+            FROM statistics
+            WHERE deleted_at IS NULL
+              AND (?1 IS NULL OR date(created_at) >= date(?1))
+              AND (?2 IS NULL OR date(created_at) <= date(?2))
+              AND (?3 IS NULL OR ticket_id GLOB ?3)
+            GROUP BY 1
+            ORDER BY 1",
+            group_by.key()
+        ))
+        .bind::<Nullable<Date>, _>(filter.since)
+        .bind::<Nullable<Date>, _>(filter.until)
+        .bind::<Nullable<Text>, _>(filter.ticket.as_ref())
+        .load(conn)
+    }
+}
+
+pub const HEADER: [&str; 6] = ["ID", "Created At", "Duration", "Ticket", "Note", "Manual"];
 
 pub fn create_table(conn: &SqliteConnection) -> QueryResult<usize> {
     sql_query(
@@ -54,17 +211,64 @@ pub fn create_table(conn: &SqliteConnection) -> QueryResult<usize> {
         , duration INTEGER NOT NULL
         , ticket_id TEXT NULL
         , note TEXT NULL
+        , manual BOOLEAN NOT NULL DEFAULT 0
+        , deleted_at TIMESTAMP NULL
+        );",
+    )
+    .execute(conn)?;
+    add_column(conn, "manual", "BOOLEAN NOT NULL DEFAULT 0")?;
+    add_column(conn, "deleted_at", "TIMESTAMP NULL")?;
+    sql_query(
+        "CREATE TABLE IF NOT EXISTS abandoned
+        ( id INTEGER PRIMARY KEY AUTOINCREMENT
+        , created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+        , duration INTEGER NOT NULL
+        , ticket_id TEXT NULL
         );",
     )
     .execute(conn)
 }
 
+// upgrades databases created by older versions
+fn add_column(conn: &SqliteConnection, name: &str, definition: &str) -> QueryResult<usize> {
+    let exists = format!(
+        "EXISTS (SELECT 1 FROM pragma_table_info('statistics') WHERE name = '{}')",
+        name
+    );
+    if select(sql::<diesel::sql_types::Bool>(&exists)).get_result(conn)? {
+        return Ok(0);
+    }
+    sql_query(format!(
+        "ALTER TABLE statistics ADD COLUMN {} {};",
+        name, definition
+    ))
+    .execute(conn)
+}
+
 impl Statistic {
     pub fn new(duration: i64) -> Statistic {
         Statistic {
-            duration: duration,
+            created_at: None,
+            duration,
             ticket_id: None,
             note: None,
+            manual: false,
+        }
+    }
+
+    /// A pomodoro entered by hand, which started at `start`.
+    pub fn manual(
+        start: NaiveDateTime,
+        duration: i64,
+        ticket_id: Option<String>,
+        note: Option<String>,
+    ) -> Statistic {
+        Statistic {
+            created_at: Some(start + Duration::minutes(duration)),
+            duration,
+            ticket_id,
+            note,
+            manual: true,
         }
     }
 
@@ -74,7 +278,7 @@ impl Statistic {
         ticket_text: Option<String>,
     ) -> QueryResult<usize> {
         use schema::statistics::dsl::*;
-        update(statistics.find(pom_id))
+        update(statistics.find(pom_id).filter(deleted_at.is_null()))
             .set(ticket_id.eq(ticket_text))
             .execute(conn)
     }
@@ -85,21 +289,96 @@ impl Statistic {
         note_text: Option<String>,
     ) -> QueryResult<usize> {
         use schema::statistics::dsl::*;
-        update(statistics.find(pom_id))
+        update(statistics.find(pom_id).filter(deleted_at.is_null()))
             .set(note.eq(note_text))
             .execute(conn)
     }
 
+    // deleted pomodoros are kept until they are purged
+    pub fn delete(conn: &SqliteConnection, pom_id: i32) -> QueryResult<usize> {
+        use schema::statistics::dsl::*;
+        update(statistics.find(pom_id).filter(deleted_at.is_null()))
+            .set(deleted_at.eq(now.nullable()))
+            .execute(conn)
+    }
+
+    pub fn restore(conn: &SqliteConnection, pom_id: i32) -> QueryResult<usize> {
+        use schema::statistics::dsl::*;
+        update(statistics.find(pom_id).filter(deleted_at.is_not_null()))
+            .set(deleted_at.eq(None::<NaiveDateTime>))
+            .execute(conn)
+    }
+
+    pub fn purge(conn: &SqliteConnection) -> QueryResult<usize> {
+        use schema::statistics::dsl::*;
+        delete(statistics.filter(deleted_at.is_not_null())).execute(conn)
+    }
+
     pub fn insert(&self, conn: &SqliteConnection) -> QueryResult<usize> {
         use schema::statistics::dsl::*;
         insert_into(statistics).values(self).execute(conn)
     }
+
+    /// Inserts it and reads the row back as stored, with its id and timestamp.
+    pub fn insert_row(&self, conn: &SqliteConnection) -> QueryResult<Pomodoro> {
+        use schema::statistics::dsl::*;
+        conn.transaction(|| {
+            self.insert(conn)?;
+            statistics.order(id.desc()).first::<Pomodoro>(conn)
+        })
+    }
+
+    // a statistic without an explicit timestamp is never a duplicate, deleted
+    // ones count so that importing the same file again does not restore them
+    pub fn is_recorded(&self, conn: &SqliteConnection) -> QueryResult<bool> {
+        use schema::statistics::dsl::*;
+        match self.created_at {
+            Some(timestamp) => select(exists(
+                statistics
+                    .filter(created_at.eq(timestamp))
+                    .filter(duration.eq(self.duration)),
+            ))
+            .get_result(conn),
+            None => Ok(false),
+        }
+    }
 }
 
 pub fn todays_no_pomodoros(conn: &SqliteConnection) -> QueryResult<i64> {
     use schema::statistics::dsl::*;
     statistics
         .select(count_star())
+        .filter(deleted_at.is_null())
         .filter(sql("date(created_at) = date('now', 'start of day')"))
         .first::<i64>(conn)
 }
+
+/// A pomodoro that was given up before it was finished, kept apart from
+/// `statistics` so it never counts as work done.
+#[derive(Insertable, PartialEq, Debug)]
+#[table_name = "abandoned"]
+pub struct Abandoned {
+    pub duration: i64,
+    pub ticket_id: Option<String>,
+}
+
+impl Abandoned {
+    pub fn insert(&self, conn: &SqliteConnection) -> QueryResult<usize> {
+        insert_into(abandoned::table).values(self).execute(conn)
+    }
+
+    pub fn count_between(
+        conn: &SqliteConnection,
+        since: NaiveDate,
+        until: NaiveDate,
+    ) -> QueryResult<i64> {
+        let sqltext = format!(
+            "date(created_at) BETWEEN date('{}') AND date('{}')",
+            since, until
+        );
+        abandoned::table
+            .select(count_star())
+            .filter(sql(&sqltext))
+            .first::<i64>(conn)
+    }
+}
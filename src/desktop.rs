@@ -0,0 +1,177 @@
+//! Notifications of the freedesktop `org.freedesktop.Notifications` service,
+//! built with the `desktop-notifications` feature. Without it, or without a
+//! session bus, `Desktop::connect` gives none and the timer goes on silently.
+
+#[cfg(feature = "desktop-notifications")]
+pub use self::bus::Desktop;
+#[cfg(not(feature = "desktop-notifications"))]
+pub use self::none::Desktop;
+
+#[cfg(not(feature = "desktop-notifications"))]
+mod none {
+    use crate::events::Event;
+    use crate::timer::{Timer, Transition};
+    use std::sync::mpsc;
+
+    pub struct Desktop;
+
+    impl Desktop {
+        pub fn connect(_tx: mpsc::Sender<Event>) -> Option<Desktop> {
+            None
+        }
+
+        pub fn notify(&self, _transition: Transition, _timer: &Timer) {}
+    }
+}
+
+#[cfg(feature = "desktop-notifications")]
+mod bus {
+    use crate::control::Command;
+    use crate::events::Event;
+    use crate::notify::message;
+    use crate::timer::{Timer, Transition};
+    use dbus::arg::PropMap;
+    use dbus::blocking::Connection;
+    use dbus::channel::Channel;
+    use dbus::message::MatchRule;
+    use std::collections::HashSet;
+    use std::sync::mpsc::{self, TryRecvError};
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time::Duration;
+
+    const SERVICE: &str = "org.freedesktop.Notifications";
+    const PATH: &str = "/org/freedesktop/Notifications";
+
+    // the key and button of each action, the key names the command it sends
+    const ACTIONS: [(&str, &str); 2] = [("skip", "Skip break"), ("pause", "Pause")];
+
+    fn actions(transition: Transition) -> &'static [(&'static str, &'static str)] {
+        match transition {
+            Transition::PomodoroFinish => &ACTIONS,
+            _ => &ACTIONS[1..],
+        }
+    }
+
+    struct Request {
+        summary: &'static str,
+        body: String,
+        actions: Vec<&'static str>,
+    }
+
+    /// Sends notifications from a thread of its own, which also turns the
+    /// actions clicked into commands of the event loop.
+    pub struct Desktop {
+        requests: mpsc::Sender<Request>,
+    }
+
+    impl Desktop {
+        pub fn connect(tx: mpsc::Sender<Event>) -> Option<Desktop> {
+            Desktop::start(tx, Connection::new_session)
+        }
+
+        /// Connects to the bus at `address` instead of the session bus.
+        pub fn connect_to(address: &str, tx: mpsc::Sender<Event>) -> Option<Desktop> {
+            let address = address.to_string();
+            Desktop::start(tx, move || {
+                let mut channel = Channel::open_private(&address)?;
+                channel.register()?;
+                Ok(Connection::from(channel))
+            })
+        }
+
+        // the connection is opened on the thread that uses it
+        fn start<F>(tx: mpsc::Sender<Event>, open: F) -> Option<Desktop>
+        where
+            F: FnOnce() -> Result<Connection, dbus::Error> + Send + 'static,
+        {
+            let (requests, rx) = mpsc::channel();
+            let (ready_tx, ready) = mpsc::channel();
+            thread::spawn(move || {
+                let conn = match open() {
+                    Ok(conn) => conn,
+                    Err(_) => return,
+                };
+                let ours = Arc::new(Mutex::new(HashSet::new()));
+                if listen(&conn, tx, ours.clone()).is_err() {
+                    return;
+                }
+                let _ = ready_tx.send(());
+                serve(&conn, rx, &ours);
+            });
+            ready.recv().ok().map(|_| Desktop { requests })
+        }
+
+        pub fn notify(&self, transition: Transition, timer: &Timer) {
+            if let Some((summary, body)) = message(transition, timer) {
+                let actions = actions(transition)
+                    .iter()
+                    .flat_map(|(key, label)| vec![*key, *label])
+                    .collect();
+                let _ = self.requests.send(Request {
+                    summary,
+                    body,
+                    actions,
+                });
+            }
+        }
+    }
+
+    // an action of a notification we sent becomes a command of the timer
+    fn listen(
+        conn: &Connection,
+        tx: mpsc::Sender<Event>,
+        ours: Arc<Mutex<HashSet<u32>>>,
+    ) -> Result<(), dbus::Error> {
+        let rule = MatchRule::new_signal(SERVICE, "ActionInvoked");
+        conn.add_match(rule, move |(id, action): (u32, String), _, _| {
+            if !ours
+                .lock()
+                .map(|mut ours| ours.remove(&id))
+                .unwrap_or(false)
+            {
+                return true;
+            }
+            let command = match action.as_str() {
+                "skip" => Command::Skip,
+                "pause" => Command::Pause,
+                _ => return true,
+            };
+            // nobody waits for the answer
+            let (reply, _) = mpsc::channel();
+            tx.send(Event::Command(command, reply)).is_ok()
+        })?;
+        Ok(())
+    }
+
+    fn serve(conn: &Connection, rx: mpsc::Receiver<Request>, ours: &Mutex<HashSet<u32>>) {
+        let proxy = conn.with_proxy(SERVICE, PATH, Duration::from_secs(5));
+        loop {
+            let _ = conn.process(Duration::from_millis(200));
+            loop {
+                let request = match rx.try_recv() {
+                    Ok(request) => request,
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Disconnected) => return,
+                };
+                let sent: Result<(u32,), _> = proxy.method_call(
+                    SERVICE,
+                    "Notify",
+                    (
+                        "pomodorust",
+                        0u32,
+                        "",
+                        request.summary,
+                        request.body,
+                        request.actions,
+                        PropMap::new(),
+                        -1i32,
+                    ),
+                );
+                if let (Ok((id,)), Ok(mut ours)) = (sent, ours.lock()) {
+                    ours.insert(id);
+                }
+            }
+        }
+    }
+}
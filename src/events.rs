@@ -1,4 +1,8 @@
-use std::io::{stdin};
+use crate::clock::{Clock, SystemClock};
+use crate::control::Command;
+use std::cmp::min;
+use std::collections::VecDeque;
+use std::io::stdin;
 use std::sync::mpsc;
 use std::thread;
 use std::time::Duration;
@@ -8,56 +12,163 @@ use termion::input::TermRead;
 pub enum Event {
     Input(Key),
     Tick(Duration),
+    // answered through the sender
+    Command(Command, mpsc::Sender<String>),
+    // a hook that ran in the background
+    HookFailed(String),
+}
+
+// how often the timer hears from its clock
+pub const TICK: Duration = Duration::from_secs(1);
+
+/// Where the event loop takes its events from.
+pub trait EventSource {
+    fn next(&mut self) -> Result<Event, mpsc::RecvError>;
 }
 
 #[allow(dead_code)]
 pub struct Events {
+    tx: mpsc::Sender<Event>,
     rx: mpsc::Receiver<Event>,
-    input_handle: thread::JoinHandle<()>,
+    input_handle: Option<thread::JoinHandle<()>>,
     time_handle: thread::JoinHandle<()>,
 }
 
+impl Default for Events {
+    fn default() -> Self {
+        Events::new()
+    }
+}
+
 impl Events {
     pub fn new() -> Events {
+        Events::with_clock(SystemClock::default(), TICK, true)
+    }
+
+    // without a terminal, only ticks and commands of the control socket
+    pub fn headless() -> Events {
+        Events::with_clock(SystemClock::default(), TICK, false)
+    }
+
+    /// Ticks every `tick` of the clock, keys are read from stdin if asked.
+    pub fn with_clock<C: Clock + 'static>(clock: C, tick: Duration, with_input: bool) -> Events {
         let (tx, rx) = mpsc::channel();
-        let input_handle = {
+        let input_handle = if with_input {
             let tx = tx.clone();
-            thread::spawn(move || {
+            Some(thread::spawn(move || {
                 let stdin = stdin();
                 let locked = stdin.lock();
                 for evt in locked.keys() {
                     match evt {
                         Ok(key) => {
-                            if let Err(_) = tx.send(Event::Input(key)) {
+                            if tx.send(Event::Input(key)).is_err() {
                                 return;
                             }
                         }
-                        Err(_) => {panic!("aaaaargh")}
+                        Err(_) => {
+                            panic!("aaaaargh")
+                        }
                     }
                 }
-            })
+            }))
+        } else {
+            None
         };
         let time_handle = {
             let tx = tx.clone();
             thread::spawn(move || {
-                let tx = tx.clone();
+                let mut last = clock.now();
                 loop {
-                    let amount = Duration::from_millis(1000);
-                    if let Err(_) = tx.send(Event::Tick(amount)) {
+                    clock.sleep(tick);
+                    let now = clock.now();
+                    if tx.send(Event::Tick(now - last)).is_err() {
                         return;
                     }
-                    thread::sleep(amount)
+                    last = now;
                 }
             })
         };
         Events {
+            tx,
             rx,
             input_handle,
             time_handle,
         }
     }
 
-    pub fn next(&self) -> Result<Event, mpsc::RecvError> {
+    // for other sources of events, like the control socket
+    pub fn sender(&self) -> mpsc::Sender<Event> {
+        self.tx.clone()
+    }
+}
+
+impl EventSource for Events {
+    fn next(&mut self) -> Result<Event, mpsc::RecvError> {
         self.rx.recv()
     }
 }
+
+/// Keys, commands and time passing given up front, for tests and
+/// simulations. Waiting
+/// ticks like the timer would, exactly as long as the script says and paced
+/// by the clock. It runs dry after the last step.
+pub struct Script<C: Clock> {
+    clock: C,
+    steps: VecDeque<Step>,
+}
+
+enum Step {
+    Key(Key),
+    Command(Command, mpsc::Sender<String>),
+    Wait(Duration),
+}
+
+impl<C: Clock> Script<C> {
+    pub fn new(clock: C) -> Script<C> {
+        Script {
+            clock,
+            steps: VecDeque::new(),
+        }
+    }
+
+    pub fn key(mut self, key: Key) -> Script<C> {
+        self.steps.push_back(Step::Key(key));
+        self
+    }
+
+    // answered through the sender like one of the control socket
+    pub fn command(mut self, command: Command, reply: mpsc::Sender<String>) -> Script<C> {
+        self.steps.push_back(Step::Command(command, reply));
+        self
+    }
+
+    // every character as a key
+    pub fn keys(self, text: &str) -> Script<C> {
+        text.chars()
+            .fold(self, |script, c| script.key(Key::Char(c)))
+    }
+
+    pub fn wait(mut self, duration: Duration) -> Script<C> {
+        self.steps.push_back(Step::Wait(duration));
+        self
+    }
+}
+
+impl<C: Clock> EventSource for Script<C> {
+    fn next(&mut self) -> Result<Event, mpsc::RecvError> {
+        loop {
+            match self.steps.pop_front() {
+                None => return Err(mpsc::RecvError),
+                Some(Step::Key(key)) => return Ok(Event::Input(key)),
+                Some(Step::Command(command, reply)) => return Ok(Event::Command(command, reply)),
+                Some(Step::Wait(left)) if left == Duration::from_secs(0) => {}
+                Some(Step::Wait(left)) => {
+                    let step = min(TICK, left);
+                    self.clock.sleep(step);
+                    self.steps.push_front(Step::Wait(left - step));
+                    return Ok(Event::Tick(step));
+                }
+            }
+        }
+    }
+}
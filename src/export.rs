@@ -0,0 +1,257 @@
+use crate::database::{Pomodoro, Worklog};
+use crate::import::TIMEW_TAG;
+use chrono::{Duration, Local, NaiveDateTime, TimeZone};
+use failure::{self, bail};
+use std::io::{self, Write};
+use std::str::FromStr;
+
+/// Break lengths used to reconstruct the breaks between recorded pomodoros.
+pub struct Breaks {
+    pub short: Duration,
+    pub long: Duration,
+}
+
+impl Breaks {
+    // breaks are not recorded, so every pomodoro is followed by the break the
+    // timer would have started, cut short when the next pomodoro began earlier
+    fn between(&self, pomodoros: &[Pomodoro]) -> Vec<(NaiveDateTime, NaiveDateTime)> {
+        let mut breaks = Vec::new();
+        let mut nth_of_day = 0;
+        for (i, pom) in pomodoros.iter().enumerate() {
+            let start = pom.finished_at();
+            match i.checked_sub(1).map(|prev| &pomodoros[prev]) {
+                Some(prev) if prev.created_at.date() == start.date() => nth_of_day += 1,
+                _ => nth_of_day = 1,
+            }
+            let length = if nth_of_day % 4 == 0 {
+                self.long
+            } else {
+                self.short
+            };
+            let end = match pomodoros.get(i + 1) {
+                Some(next) => (start + length).min(next.started_at()),
+                None => start + length,
+            };
+            if start < end {
+                breaks.push((start, end));
+            }
+        }
+        breaks
+    }
+}
+
+/// Writes the pomodoros as an iCalendar file with one `VEVENT` each.
+pub fn ics<W: Write>(
+    out: &mut W,
+    pomodoros: &[Pomodoro],
+    breaks: Option<&Breaks>,
+) -> io::Result<()> {
+    let stamp = ics_time(chrono::Utc::now().naive_utc());
+    write_ics_line(out, "BEGIN:VCALENDAR")?;
+    write_ics_line(out, "VERSION:2.0")?;
+    write_ics_line(out, "PRODID:-//pomodorust//pomodorust//EN")?;
+    for pom in pomodoros {
+        write_ics_line(out, "BEGIN:VEVENT")?;
+        write_ics_line(out, &format!("UID:pomodoro-{}@pomodorust", pom.id))?;
+        write_ics_line(out, &format!("DTSTAMP:{}", stamp))?;
+        write_ics_line(out, &format!("DTSTART:{}", ics_time(pom.started_at())))?;
+        write_ics_line(out, &format!("DTEND:{}", ics_time(pom.finished_at())))?;
+        let summary = pom.ticket_id.as_deref().unwrap_or("Pomodoro");
+        write_ics_line(out, &format!("SUMMARY:{}", ics_escape(summary)))?;
+        if let Some(note) = &pom.note {
+            write_ics_line(out, &format!("DESCRIPTION:{}", ics_escape(note)))?;
+        }
+        write_ics_line(out, "END:VEVENT")?;
+    }
+    if let Some(breaks) = breaks {
+        for (start, end) in breaks.between(pomodoros) {
+            write_ics_line(out, "BEGIN:VEVENT")?;
+            write_ics_line(out, &format!("UID:break-{}@pomodorust", ics_time(start)))?;
+            write_ics_line(out, &format!("DTSTAMP:{}", stamp))?;
+            write_ics_line(out, &format!("DTSTART:{}", ics_time(start)))?;
+            write_ics_line(out, &format!("DTEND:{}", ics_time(end)))?;
+            write_ics_line(out, "SUMMARY:Break")?;
+            write_ics_line(out, "TRANSP:TRANSPARENT")?;
+            write_ics_line(out, "END:VEVENT")?;
+        }
+    }
+    write_ics_line(out, "END:VCALENDAR")
+}
+
+// created_at is stored in UTC
+fn ics_time(time: NaiveDateTime) -> String {
+    time.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+fn ics_escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+// content lines are folded after 75 octets, continuation lines start with a space
+fn write_ics_line<W: Write>(out: &mut W, line: &str) -> io::Result<()> {
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > 75 {
+            out.write_all(b"\r\n ")?;
+            width = 1;
+        }
+        write!(out, "{}", c)?;
+        width += c.len_utf8();
+    }
+    out.write_all(b"\r\n")
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RoundingMode {
+    Up,
+    Down,
+    Nearest,
+}
+
+impl FromStr for RoundingMode {
+    type Err = failure::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "up" => Ok(RoundingMode::Up),
+            "down" => Ok(RoundingMode::Down),
+            "nearest" => Ok(RoundingMode::Nearest),
+            _ => bail!("Unknown rounding '{}', expected up, down or nearest.", s),
+        }
+    }
+}
+
+/// Rounds logged minutes to multiples of `step_mins`.
+pub struct Rounding {
+    pub step_mins: i64,
+    pub mode: RoundingMode,
+}
+
+impl Rounding {
+    // never rounds logged time away completely
+    pub fn round(&self, mins: i64) -> i64 {
+        if self.step_mins <= 1 {
+            return mins;
+        }
+        let steps = match self.mode {
+            RoundingMode::Up => (mins + self.step_mins - 1) / self.step_mins,
+            RoundingMode::Down => mins / self.step_mins,
+            RoundingMode::Nearest => (mins + self.step_mins / 2) / self.step_mins,
+        };
+        if steps == 0 && mins > 0 {
+            self.step_mins
+        } else {
+            steps * self.step_mins
+        }
+    }
+}
+
+/// Writes the worklogs in the layout of Jira's csv importer: the issue key and
+/// a `Log Work` column of the form `comment;date;author;seconds`.
+pub fn jira_worklog<W: Write>(
+    out: W,
+    worklogs: &[Worklog],
+    rounding: &Rounding,
+    author: &str,
+) -> Result<(), failure::Error> {
+    let mut csv = csv::Writer::from_writer(out);
+    csv.write_record(["Issue Key", "Log Work"])?;
+    for log in worklogs {
+        // ';' separates the parts of a worklog entry
+        let comment = log.notes.as_deref().unwrap_or_default().replace(';', ",");
+        let entry = format!(
+            "{};{};{};{}",
+            comment,
+            log.started_at.format("%Y-%m-%d %H:%M"),
+            author,
+            rounding.round(log.duration) * 60
+        );
+        csv.write_record([log.ticket_id.as_str(), entry.as_str()])?;
+    }
+    csv.flush()?;
+    Ok(())
+}
+
+/// Writes the pomodoros as lines of timewarrior's data files, tagged with
+/// `pomodoro` and the ticket, the note becomes the annotation.
+pub fn timew<W: Write>(out: &mut W, pomodoros: &[Pomodoro]) -> io::Result<()> {
+    for pom in pomodoros {
+        write!(
+            out,
+            "inc {} - {} # {}",
+            ics_time(pom.started_at()),
+            ics_time(pom.finished_at()),
+            TIMEW_TAG
+        )?;
+        if let Some(ticket) = &pom.ticket_id {
+            write!(out, " {}", timew_tag(ticket))?;
+        }
+        if let Some(note) = &pom.note {
+            write!(out, " # {}", timew_quote(note))?;
+        }
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+fn timew_tag(tag: &str) -> String {
+    if tag
+        .chars()
+        .any(|c| c.is_whitespace() || c == '"' || c == '#')
+    {
+        timew_quote(tag)
+    } else {
+        tag.to_string()
+    }
+}
+
+fn timew_quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Writes one org heading per ticket holding the `CLOCK` lines of its
+/// pomodoros in a logbook drawer, followed by their notes.
+pub fn org<W: Write>(out: &mut W, pomodoros: &[Pomodoro]) -> io::Result<()> {
+    let mut tickets: Vec<(Option<&String>, Vec<&Pomodoro>)> = Vec::new();
+    for pom in pomodoros {
+        let ticket = pom.ticket_id.as_ref();
+        match tickets.iter_mut().find(|(t, _)| *t == ticket) {
+            Some((_, poms)) => poms.push(pom),
+            None => tickets.push((ticket, vec![pom])),
+        }
+    }
+    for (ticket, poms) in tickets {
+        writeln!(
+            out,
+            "* {}",
+            ticket.map(String::as_str).unwrap_or("Pomodoros")
+        )?;
+        writeln!(out, "  :LOGBOOK:")?;
+        for pom in &poms {
+            writeln!(
+                out,
+                "  CLOCK: {}--{} => {:2}:{:02}",
+                org_time(pom.started_at()),
+                org_time(pom.finished_at()),
+                pom.duration / 60,
+                pom.duration % 60
+            )?;
+        }
+        writeln!(out, "  :END:")?;
+        for note in poms.iter().filter_map(|pom| pom.note.as_ref()) {
+            writeln!(out, "  {}", note)?;
+        }
+    }
+    Ok(())
+}
+
+// org timestamps carry no zone, so they are shown in local time
+fn org_time(time: NaiveDateTime) -> String {
+    Local
+        .from_utc_datetime(&time)
+        .format("[%Y-%m-%d %a %H:%M]")
+        .to_string()
+}
@@ -0,0 +1,88 @@
+use serde::{Deserialize, Serialize};
+use termion::event::Key;
+use tui::backend::Backend;
+use tui::layout::Rect;
+use tui::style::{Color, Modifier, Style};
+use tui::terminal::Frame;
+use tui::widgets::{Block, Borders, Paragraph, Text, Widget};
+
+/// A few labelled text fields that are filled in from within the TUI.
+#[derive(Serialize, Deserialize)]
+pub struct Form {
+    pub title: String,
+    pub fields: Vec<(String, String)>,
+    pub focus: usize,
+    pub error: Option<String>,
+}
+
+pub enum FormEvent {
+    Submit,
+    Cancel,
+}
+
+impl Form {
+    pub fn new(title: &str, fields: Vec<(&'static str, String)>) -> Form {
+        Form {
+            title: String::from(title),
+            fields: fields
+                .into_iter()
+                .map(|(label, value)| (String::from(label), value))
+                .collect(),
+            focus: 0,
+            error: None,
+        }
+    }
+
+    pub fn value(&self, field: usize) -> &str {
+        self.fields[field].1.trim()
+    }
+
+    // tab and the arrow keys move between fields, enter submits, esc cancels
+    pub fn key_handler(&mut self, key: Key) -> Option<FormEvent> {
+        match key {
+            Key::Char('\n') => return Some(FormEvent::Submit),
+            Key::Esc => return Some(FormEvent::Cancel),
+            Key::Char('\t') | Key::Down => self.focus = (self.focus + 1) % self.fields.len(),
+            Key::BackTab | Key::Up => {
+                self.focus = (self.focus + self.fields.len() - 1) % self.fields.len()
+            }
+            Key::Backspace => {
+                self.fields[self.focus].1.pop();
+            }
+            Key::Char(c) => self.fields[self.focus].1.push(c),
+            _ => {}
+        }
+        None
+    }
+
+    pub fn paragraph<B>(&self, f: &mut Frame<B>, area: Rect)
+    where
+        B: Backend,
+    {
+        let focused = Style::default().fg(Color::Yellow).modifier(Modifier::BOLD);
+        let mut content = Vec::new();
+        for (i, (label, value)) in self.fields.iter().enumerate() {
+            let text = format!("{:>10}: {}\n", label, value);
+            if i == self.focus {
+                content.push(Text::styled(text, focused));
+            } else {
+                content.push(Text::raw(text));
+            }
+        }
+        if let Some(error) = &self.error {
+            content.push(Text::styled(
+                format!("{}\n", error),
+                Style::default().fg(Color::Red),
+            ));
+        }
+        content.push(Text::raw("Enter to save, Esc to cancel."));
+        Paragraph::new(content.iter())
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(&self.title)
+                    .title_style(Style::default().fg(Color::Yellow)),
+            )
+            .render(f, area);
+    }
+}
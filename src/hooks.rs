@@ -0,0 +1,100 @@
+use crate::timer::{Timer, Transition, TRANSITIONS};
+use failure::{self, bail};
+use std::collections::HashMap;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Shell commands run on transitions of the timer, configured in `hooks:`.
+pub struct Hooks {
+    commands: HashMap<Transition, String>,
+    timeout: Duration,
+}
+
+impl Hooks {
+    pub fn new(
+        commands: &HashMap<String, String>,
+        timeout: Duration,
+    ) -> Result<Hooks, failure::Error> {
+        let mut parsed = HashMap::new();
+        for (name, command) in commands {
+            match name.parse() {
+                Ok(transition) => parsed.insert(transition, command.clone()),
+                Err(_) => bail!(
+                    "Unknown hook '{}', hooks are {}.",
+                    name,
+                    TRANSITIONS
+                        .iter()
+                        .map(|t| t.name())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+            };
+        }
+        Ok(Hooks {
+            commands: parsed,
+            timeout,
+        })
+    }
+
+    /// Runs the hook of the transition in the background, a failure is passed
+    /// to `failed` from there so that the timer never waits for it.
+    pub fn run<F>(&self, timer: &Timer, transition: Transition, length: Duration, failed: F)
+    where
+        F: FnOnce(String) + Send + 'static,
+    {
+        let command = match self.commands.get(&transition) {
+            Some(command) => command.clone(),
+            None => return,
+        };
+        let env = vec![
+            ("POMODORUST_TRANSITION", transition.name().to_string()),
+            ("POMODORUST_STATE", timer.state.name().to_string()),
+            (
+                "POMODORUST_TICKET",
+                timer.ticket.clone().unwrap_or_default(),
+            ),
+            ("POMODORUST_DURATION", (length.as_secs() / 60).to_string()),
+            ("POMODORUST_COUNT", timer.todays_pomodoros.to_string()),
+        ];
+        let timeout = self.timeout;
+        thread::spawn(move || {
+            if let Err(e) = execute(&command, env, timeout) {
+                failed(format!("{}: {}", transition.name(), e));
+            }
+        });
+    }
+}
+
+fn execute(
+    command: &str,
+    env: Vec<(&str, String)>,
+    timeout: Duration,
+) -> Result<(), failure::Error> {
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .envs(env)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    let started = Instant::now();
+    loop {
+        if let Some(status) = child.try_wait()? {
+            if status.success() {
+                return Ok(());
+            }
+            match status.code() {
+                Some(code) => bail!("exited with {}", code),
+                None => bail!("killed by a signal"),
+            }
+        }
+        if started.elapsed() >= timeout {
+            let _ = child.kill();
+            let _ = child.wait();
+            bail!("timed out after {}s", timeout.as_secs());
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+}
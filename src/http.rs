@@ -0,0 +1,170 @@
+use crate::control::Command;
+use crate::database::{Aggregate, Filter, GroupBy, Pomodoro};
+use crate::events::Event;
+use chrono::{NaiveDate, Utc};
+use clap::ArgMatches;
+use diesel::prelude::Connection;
+use diesel::sqlite::SqliteConnection;
+use failure::{self, bail, format_err};
+use serde_json::{json, Value};
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::thread;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+/// Where the API is served, given with `--http`. Only loopback addresses
+/// unless `--http-public` says otherwise, nothing asks who sends a command.
+#[derive(Clone, Copy)]
+pub struct Address<'a> {
+    pub address: &'a str,
+    pub public: bool,
+}
+
+impl<'a> Address<'a> {
+    pub fn of(args: &'a ArgMatches) -> Option<Address<'a>> {
+        args.value_of("http").map(|address| Address {
+            address,
+            public: args.is_present("http-public"),
+        })
+    }
+}
+
+/// Serves the timer as JSON for small integrations:
+///
+/// * `GET /status` and `GET /state`, the status and everything the TUI shows
+/// * `GET /pomodoros`, today's pomodoros
+/// * `GET /stats?since=&until=&ticket=&group_by=`, like the `stats` command
+/// * `POST /pause`, `/resume`, `/skip` and `/abandon`
+///
+/// State and control go through the event loop like the control socket,
+/// history is read with a database connection of its own. Returns where it
+/// listens, which tells the port when asked for any.
+pub fn serve(
+    address: Address,
+    db_path: PathBuf,
+    tx: mpsc::Sender<Event>,
+) -> Result<SocketAddr, failure::Error> {
+    let Address { address, public } = address;
+    let resolved: Vec<SocketAddr> = address
+        .to_socket_addrs()
+        .map_err(|e| format_err!("{}: {}", address, e))?
+        .collect();
+    if !public && !resolved.iter().all(|addr| addr.ip().is_loopback()) {
+        bail!(
+            "{} is not a loopback address, `--http-public` serves the API on it anyway.",
+            address
+        );
+    }
+    let server = Server::http(&resolved[..]).map_err(|e| format_err!("{}: {}", address, e))?;
+    let listening = server
+        .server_addr()
+        .to_ip()
+        .ok_or_else(|| format_err!("{}: not an IP address", address))?;
+    thread::spawn(move || {
+        let conn = match SqliteConnection::establish(&db_path.to_string_lossy()) {
+            Ok(conn) => conn,
+            Err(_) => return,
+        };
+        for request in server.incoming_requests() {
+            let (status, body) = match route(&request, &conn, &tx) {
+                Ok(body) => (200, body),
+                Err(Failure(status, message)) => (status, json!({ "error": message })),
+            };
+            let header =
+                Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).unwrap();
+            let response = Response::from_string(body.to_string())
+                .with_status_code(status)
+                .with_header(header);
+            let _ = request.respond(response);
+        }
+    });
+    Ok(listening)
+}
+
+struct Failure(u16, String);
+
+impl From<failure::Error> for Failure {
+    fn from(e: failure::Error) -> Failure {
+        Failure(400, e.to_string())
+    }
+}
+
+impl From<diesel::result::Error> for Failure {
+    fn from(e: diesel::result::Error) -> Failure {
+        Failure(500, e.to_string())
+    }
+}
+
+fn route(
+    request: &Request,
+    conn: &SqliteConnection,
+    tx: &mpsc::Sender<Event>,
+) -> Result<Value, Failure> {
+    let mut url = request.url().splitn(2, '?');
+    let path = url.next().unwrap_or_default();
+    let query: Vec<(String, String)> =
+        url::form_urlencoded::parse(url.next().unwrap_or_default().as_bytes())
+            .into_owned()
+            .collect();
+    let param = |name: &str| {
+        query
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+            .filter(|value| !value.is_empty())
+    };
+    match (request.method(), path) {
+        (Method::Get, "/status") => command(tx, Command::Status),
+        (Method::Get, "/state") => command(tx, Command::Snapshot),
+        (Method::Get, "/pomodoros") => {
+            let pomodoros = Pomodoro::pomodoros_of(conn, Utc::now().naive_utc())?;
+            Ok(json!(pomodoros))
+        }
+        (Method::Get, "/stats") => {
+            let filter = Filter {
+                since: date(param("since"))?,
+                until: date(param("until"))?,
+                ticket: param("ticket").map(String::from),
+            };
+            let group_by: GroupBy = param("group_by").unwrap_or("day").parse()?;
+            Ok(json!(Aggregate::aggregates(conn, &filter, group_by)?))
+        }
+        (Method::Post, "/pause") => command(tx, Command::Pause),
+        (Method::Post, "/resume") => command(tx, Command::Resume),
+        (Method::Post, "/skip") => command(tx, Command::Skip),
+        (Method::Post, "/abandon") => command(tx, Command::Abandon),
+        (_, "/status")
+        | (_, "/state")
+        | (_, "/pomodoros")
+        | (_, "/stats")
+        | (_, "/pause")
+        | (_, "/resume")
+        | (_, "/skip")
+        | (_, "/abandon") => Err(Failure(405, String::from("method not allowed"))),
+        _ => Err(Failure(404, format!("no such endpoint '{}'", path))),
+    }
+}
+
+// the answer of the timer, which is JSON or "ok" or an error
+fn command(tx: &mpsc::Sender<Event>, command: Command) -> Result<Value, Failure> {
+    let (reply_tx, reply) = mpsc::channel();
+    let stopped = || Failure(503, String::from("timer stopped"));
+    tx.send(Event::Command(command, reply_tx))
+        .map_err(|_| stopped())?;
+    let answer = reply.recv().map_err(|_| stopped())?;
+    if let Some(error) = answer.strip_prefix("error: ") {
+        return Err(Failure(409, String::from(error)));
+    }
+    Ok(serde_json::from_str(&answer).unwrap_or_else(|_| json!({ "result": answer })))
+}
+
+fn date(text: Option<&str>) -> Result<Option<NaiveDate>, failure::Error> {
+    match text {
+        Some(text) => match NaiveDate::parse_from_str(text, "%Y-%m-%d") {
+            Ok(date) => Ok(Some(date)),
+            Err(_) => bail!("'{}' is not a date like 2020-01-31", text),
+        },
+        None => Ok(None),
+    }
+}
@@ -0,0 +1,212 @@
+use crate::database::Statistic;
+use chrono::{DateTime, Local, LocalResult, NaiveDateTime, TimeZone, Timelike};
+use diesel::prelude::*;
+use failure::{self, bail, format_err};
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::io::Read;
+
+/// Maps the fields of `statistics` onto the header names of a csv file.
+#[derive(Debug, PartialEq)]
+pub struct ColumnMapping {
+    pub created_at: String,
+    pub duration: String,
+    pub ticket_id: String,
+    pub note: String,
+}
+
+impl Default for ColumnMapping {
+    fn default() -> Self {
+        ColumnMapping {
+            created_at: String::from("created_at"),
+            duration: String::from("duration"),
+            ticket_id: String::from("ticket_id"),
+            note: String::from("note"),
+        }
+    }
+}
+
+impl ColumnMapping {
+    /// Parses a mapping like `created_at=Start,duration=Minutes`, fields that
+    /// are not mentioned keep their default column name.
+    pub fn parse(spec: &str) -> Result<ColumnMapping, failure::Error> {
+        let mut mapping = ColumnMapping::default();
+        for pair in spec.split(',').filter(|p| !p.trim().is_empty()) {
+            let mut parts = pair.splitn(2, '=');
+            let field = parts.next().unwrap_or_default().trim();
+            let column = match parts.next() {
+                Some(column) => column.trim().to_string(),
+                None => bail!("Column mapping '{}' is not of the form field=column.", pair),
+            };
+            match field {
+                "created_at" => mapping.created_at = column,
+                "duration" => mapping.duration = column,
+                "ticket_id" => mapping.ticket_id = column,
+                "note" => mapping.note = column,
+                _ => bail!("Unknown field '{}' in column mapping.", field),
+            }
+        }
+        Ok(mapping)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    pub inserted: Vec<Statistic>,
+    pub duplicates: Vec<Statistic>,
+}
+
+/// Reads pomodoros from csv and inserts every row that is not yet recorded,
+/// two rows are the same pomodoro if their timestamp and duration match.
+/// With `dry_run` nothing is written, the report shows what would be inserted.
+pub fn import_csv<R: Read>(
+    conn: &SqliteConnection,
+    reader: R,
+    mapping: &ColumnMapping,
+    dry_run: bool,
+) -> Result<ImportReport, failure::Error> {
+    let mut csv = csv::Reader::from_reader(reader);
+    let headers = csv.headers()?.clone();
+    let position = |column: &str| headers.iter().position(|h| h.trim() == column);
+    let created_at_col = position(&mapping.created_at)
+        .ok_or_else(|| format_err!("Missing column '{}'.", mapping.created_at))?;
+    let duration_col = position(&mapping.duration)
+        .ok_or_else(|| format_err!("Missing column '{}'.", mapping.duration))?;
+    let ticket_col = position(&mapping.ticket_id);
+    let note_col = position(&mapping.note);
+
+    let mut rows = Vec::new();
+    for (line, record) in csv.records().enumerate() {
+        let record = record?;
+        let field = |col: Option<usize>| {
+            col.and_then(|c| record.get(c))
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+                .map(String::from)
+        };
+        let created_at = parse_timestamp(record.get(created_at_col).unwrap_or_default())
+            .map_err(|e| format_err!("Row {}: {}", line + 1, e))?;
+        let duration = record
+            .get(duration_col)
+            .unwrap_or_default()
+            .trim()
+            .parse::<i64>()
+            .map_err(|e| format_err!("Row {}: invalid duration ({}).", line + 1, e))?;
+        rows.push(Statistic {
+            created_at: Some(created_at),
+            duration,
+            ticket_id: field(ticket_col),
+            note: field(note_col),
+            manual: true,
+        });
+    }
+    insert_new(conn, rows, dry_run)
+}
+
+/// Tag that marks intervals written by the timewarrior export.
+pub const TIMEW_TAG: &str = "pomodoro";
+
+#[derive(Deserialize)]
+struct TimewInterval {
+    start: String,
+    end: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    annotation: Option<String>,
+}
+
+/// Reads the json of `timew export`, the first tag apart from `pomodoro`
+/// becomes the ticket and the annotation the note. Open intervals are skipped.
+pub fn import_timew<R: Read>(
+    conn: &SqliteConnection,
+    reader: R,
+    dry_run: bool,
+) -> Result<ImportReport, failure::Error> {
+    let intervals: Vec<TimewInterval> = serde_json::from_reader(reader)?;
+    let mut rows = Vec::new();
+    for interval in intervals {
+        let end = match interval.end {
+            Some(end) => parse_timew_time(&end)?,
+            None => continue,
+        };
+        let start = parse_timew_time(&interval.start)?;
+        rows.push(Statistic {
+            created_at: Some(end),
+            duration: ((end - start).num_seconds().max(0) + 30) / 60,
+            ticket_id: interval.tags.into_iter().find(|tag| tag != TIMEW_TAG),
+            note: interval.annotation.filter(|a| !a.is_empty()),
+            manual: true,
+        });
+    }
+    insert_new(conn, rows, dry_run)
+}
+
+fn parse_timew_time(text: &str) -> Result<NaiveDateTime, failure::Error> {
+    NaiveDateTime::parse_from_str(text, "%Y%m%dT%H%M%SZ")
+        .map_err(|e| format_err!("Cannot parse timewarrior time '{}' ({}).", text, e))
+}
+
+fn insert_new(
+    conn: &SqliteConnection,
+    rows: Vec<Statistic>,
+    dry_run: bool,
+) -> Result<ImportReport, failure::Error> {
+    conn.transaction(|| {
+        let mut report = ImportReport::default();
+        let mut seen = HashSet::new();
+        for stat in rows {
+            if !seen.insert((stat.created_at, stat.duration)) || stat.is_recorded(conn)? {
+                report.duplicates.push(stat);
+                continue;
+            }
+            if !dry_run {
+                stat.insert(conn)?;
+            }
+            report.inserted.push(stat);
+        }
+        Ok(report)
+    })
+}
+
+/// Accepts `YYYY-MM-DD HH:MM[:SS]`, the same with a `T` separator, and
+/// RFC 3339. Timestamps without an offset are taken as UTC like `created_at`.
+pub fn parse_timestamp(text: &str) -> Result<NaiveDateTime, failure::Error> {
+    let text = text.trim();
+    match parse_rfc3339(text) {
+        Some(timestamp) => Ok(timestamp),
+        None => parse_naive(text),
+    }
+}
+
+/// Like `parse_timestamp`, but a time typed by hand without an offset is
+/// local time. Returns it in UTC like `created_at`.
+pub fn parse_local_timestamp(text: &str) -> Result<NaiveDateTime, failure::Error> {
+    let text = text.trim();
+    if let Some(timestamp) = parse_rfc3339(text) {
+        return Ok(timestamp);
+    }
+    match Local.from_local_datetime(&parse_naive(text)?) {
+        // the earlier of the two when the clocks are turned back
+        LocalResult::Single(local) | LocalResult::Ambiguous(local, _) => Ok(local.naive_utc()),
+        LocalResult::None => bail!("'{}' is skipped when the clocks are turned forward.", text),
+    }
+}
+
+fn parse_rfc3339(text: &str) -> Option<NaiveDateTime> {
+    let timestamp = DateTime::parse_from_rfc3339(text).ok()?;
+    Some(timestamp.naive_utc().with_nanosecond(0).unwrap())
+}
+
+fn parse_naive(text: &str) -> Result<NaiveDateTime, failure::Error> {
+    for format in &[
+        "%Y-%m-%d %H:%M:%S",
+        "%Y-%m-%dT%H:%M:%S",
+        "%Y-%m-%d %H:%M",
+        "%Y-%m-%dT%H:%M",
+    ] {
+        if let Ok(timestamp) = NaiveDateTime::parse_from_str(text, format) {
+            return Ok(timestamp);
+        }
+    }
+    bail!("Cannot parse timestamp '{}'.", text)
+}
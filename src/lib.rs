@@ -1,8 +1,53 @@
+// diesel 1.x derives expand to impls inside anonymous consts
+#![allow(non_local_definitions)]
+
+//! Without default features only the timer core is built: `timer` and the
+//! `clock` it runs on, but neither the terminal interface, the database nor
+//! the program around them.
+
+#[cfg(feature = "sqlite")]
 #[macro_use]
 extern crate diesel;
 
+#[cfg(feature = "app")]
+pub mod cli;
+pub mod clock;
+#[cfg(feature = "app")]
 pub mod config;
+#[cfg(all(feature = "tui", feature = "sqlite", feature = "app"))]
+pub mod control;
+#[cfg(feature = "sqlite")]
 pub mod database;
+#[cfg(all(feature = "tui", feature = "sqlite", feature = "app"))]
+pub mod desktop;
+#[cfg(all(feature = "tui", feature = "sqlite", feature = "app"))]
 pub mod events;
+#[cfg(feature = "sqlite")]
+pub mod export;
+#[cfg(feature = "tui")]
+pub mod form;
+#[cfg(feature = "app")]
+pub mod hooks;
+#[cfg(all(feature = "tui", feature = "sqlite", feature = "app"))]
+pub mod http;
+#[cfg(feature = "sqlite")]
+pub mod import;
+#[cfg(feature = "app")]
+pub mod lock;
+#[cfg(feature = "app")]
+pub mod metrics;
+#[cfg(feature = "app")]
+pub mod notify;
+#[cfg(all(feature = "tui", feature = "sqlite", feature = "app"))]
+pub mod render;
+#[cfg(feature = "sqlite")]
+pub mod report;
+#[cfg(feature = "sqlite")]
 pub mod schema;
+#[cfg(all(feature = "tui", feature = "sqlite", feature = "app"))]
 pub mod state;
+#[cfg(feature = "app")]
+pub mod status;
+pub mod timer;
+#[cfg(feature = "app")]
+pub mod webhook;
@@ -0,0 +1,120 @@
+use failure::{self, bail};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::os::unix::fs::MetadataExt;
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+use std::process;
+
+/// Marks a database as used by a running timer, so that a second one does not
+/// count and insert the same pomodoros. The file holds the PID of the timer,
+/// which keeps it flocked while it runs, and is removed again when dropped.
+pub struct Lock {
+    path: PathBuf,
+    file: File,
+}
+
+pub fn lock_path(db_path: &Path) -> PathBuf {
+    let mut name = db_path.as_os_str().to_owned();
+    name.push(".lock");
+    PathBuf::from(name)
+}
+
+/// The PID of the timer running on the database, if it is still alive.
+pub fn holder(db_path: &Path) -> Option<i32> {
+    let mut file = File::open(lock_path(db_path)).ok()?;
+    if flock(&file, libc::LOCK_SH | libc::LOCK_NB) {
+        return None;
+    }
+    read_pid(&mut file)
+}
+
+fn read_pid(file: &mut File) -> Option<i32> {
+    let mut pid = String::new();
+    file.read_to_string(&mut pid).ok()?;
+    pid.trim().parse().ok()
+}
+
+// the kernel lets go of it when the process is gone, however it ended
+fn flock(file: &File, operation: libc::c_int) -> bool {
+    unsafe { libc::flock(file.as_raw_fd(), operation) == 0 }
+}
+
+// whether the path still names the file that was opened
+fn is_at(file: &File, path: &Path) -> bool {
+    match (file.metadata(), fs::metadata(path)) {
+        (Ok(opened), Ok(named)) => opened.dev() == named.dev() && opened.ino() == named.ino(),
+        _ => false,
+    }
+}
+
+impl Lock {
+    /// Fails while another timer holds the lock, unless forced. A lock left
+    /// behind by a process that is gone is taken over.
+    pub fn acquire(db_path: &Path, force: bool) -> Result<Lock, failure::Error> {
+        let path = lock_path(db_path);
+        // other timers may be taking over the same stale lock
+        for _ in 0..3 {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(mut file) => {
+                    // locked before the PID is in, one checking it for being
+                    // stale in between only holds it for a moment
+                    if !flock(&file, libc::LOCK_EX) {
+                        return Err(io::Error::last_os_error().into());
+                    }
+                    writeln!(file, "{}", process::id())?;
+                    return Ok(Lock { path, file });
+                }
+                Err(ref e) if e.kind() == io::ErrorKind::AlreadyExists => {}
+                Err(e) => return Err(e.into()),
+            }
+            take_over(&path, db_path, force)?;
+        }
+        bail!(
+            "Other timers keep taking the lock of {}.",
+            db_path.display()
+        )
+    }
+}
+
+// removes a lock whose timer is gone, or any when forced, so that the next
+// attempt can create it anew
+fn take_over(path: &Path, db_path: &Path, force: bool) -> Result<(), failure::Error> {
+    let mut file = match File::open(path) {
+        Ok(file) => file,
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+    // nobody else removes it while this holds the flock
+    let unlocked = flock(&file, libc::LOCK_EX | libc::LOCK_NB);
+    match read_pid(&mut file) {
+        // an unreadable PID may be one that is being written
+        None if !force => bail!(
+            "{} is taken by another timer or damaged, remove it if no timer is running.",
+            path.display()
+        ),
+        Some(pid) if !unlocked && !force => bail!(
+            "Another timer (PID {}) is running on {}.",
+            pid,
+            db_path.display()
+        ),
+        _ => {}
+    }
+    if is_at(&file, path) {
+        if let Err(e) = fs::remove_file(path) {
+            if e.kind() != io::ErrorKind::NotFound {
+                return Err(e.into());
+            }
+        }
+    }
+    Ok(())
+}
+
+impl Drop for Lock {
+    // a forced timer may have taken the lock over in the meantime
+    fn drop(&mut self) {
+        if is_at(&self.file, &self.path) {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
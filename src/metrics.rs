@@ -0,0 +1,178 @@
+#[cfg(feature = "sqlite")]
+use crate::database::{Aggregate, Filter, GroupBy};
+use crate::status::Status;
+#[cfg(feature = "sqlite")]
+use diesel::{result::QueryResult, sqlite::SqliteConnection};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+#[cfg(all(feature = "tui", feature = "sqlite"))]
+use {
+    crate::control::Command,
+    crate::events::Event,
+    diesel::prelude::Connection,
+    failure::{self, format_err},
+    std::sync::mpsc,
+    std::thread,
+    tiny_http::{Header, Response, Server},
+};
+
+/// Where to export metrics in the Prometheus format, configured in `metrics:`.
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct Metrics {
+    // for the textfile collector of node_exporter, rewritten as the timer runs
+    pub textfile: Option<PathBuf>,
+    // served at /metrics
+    pub listen: Option<String>,
+}
+
+/// The numbers exported, counted from the recorded pomodoros and the
+/// running timer. Deleting pomodoros lowers the counts, so they are gauges.
+pub struct Sample {
+    pub status: Status,
+    pub completed: i64,
+    pub focused_secs: i64,
+    // untracked pomodoros under the empty ticket
+    pub tickets: Vec<(String, i64)>,
+}
+
+const STATES: [&str; 3] = ["running", "break", "paused"];
+
+impl Sample {
+    #[cfg(feature = "sqlite")]
+    pub fn gather(conn: &SqliteConnection, status: Status) -> QueryResult<Sample> {
+        let aggregates = Aggregate::aggregates(conn, &Filter::default(), GroupBy::Ticket)?;
+        Ok(Sample {
+            status,
+            completed: aggregates.iter().map(|agg| agg.count).sum(),
+            focused_secs: aggregates.iter().map(|agg| agg.duration * 60).sum(),
+            tickets: aggregates
+                .into_iter()
+                .map(|agg| (agg.group.unwrap_or_default(), agg.count))
+                .collect(),
+        })
+    }
+
+    /// The text exposition format.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        metric(
+            &mut out,
+            "pomodorust_pomodoros_completed",
+            "gauge",
+            "Pomodoros recorded and not deleted.",
+        );
+        let _ = writeln!(out, "pomodorust_pomodoros_completed {}", self.completed);
+        metric(
+            &mut out,
+            "pomodorust_focused_seconds",
+            "gauge",
+            "Time spent in recorded pomodoros.",
+        );
+        let _ = writeln!(out, "pomodorust_focused_seconds {}", self.focused_secs);
+        metric(
+            &mut out,
+            "pomodorust_ticket_pomodoros",
+            "gauge",
+            "Pomodoros recorded per ticket, untracked ones have an empty ticket.",
+        );
+        for (ticket, count) in &self.tickets {
+            let _ = writeln!(
+                out,
+                "pomodorust_ticket_pomodoros{{ticket=\"{}\"}} {}",
+                escape(ticket),
+                count
+            );
+        }
+        metric(
+            &mut out,
+            "pomodorust_state",
+            "gauge",
+            "State of the timer, 1 for the current one.",
+        );
+        for state in &STATES {
+            let current = if self.status.state == *state { 1 } else { 0 };
+            let _ = writeln!(out, "pomodorust_state{{state=\"{}\"}} {}", state, current);
+        }
+        metric(
+            &mut out,
+            "pomodorust_remaining_seconds",
+            "gauge",
+            "Until the pomodoro or break ends.",
+        );
+        let _ = writeln!(
+            out,
+            "pomodorust_remaining_seconds {}",
+            self.status.remaining_secs
+        );
+        metric(
+            &mut out,
+            "pomodorust_pomodoros_today",
+            "gauge",
+            "Pomodoros recorded today.",
+        );
+        let _ = writeln!(out, "pomodorust_pomodoros_today {}", self.status.count);
+        out
+    }
+}
+
+fn metric(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+fn escape(label: &str) -> String {
+    label
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Replaces the file at once, the collector must never read half of it.
+pub fn write_textfile(path: &Path, text: &str) -> io::Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    fs::write(&tmp, text)?;
+    fs::rename(tmp, path)
+}
+
+/// Serves `/metrics`, the state comes from the event loop and the counters
+/// from a database connection of its own.
+#[cfg(all(feature = "tui", feature = "sqlite"))]
+pub fn serve(
+    address: &str,
+    db_path: PathBuf,
+    tx: mpsc::Sender<Event>,
+) -> Result<thread::JoinHandle<()>, failure::Error> {
+    let server = Server::http(address).map_err(|e| format_err!("{}: {}", address, e))?;
+    Ok(thread::spawn(move || {
+        let conn = match SqliteConnection::establish(&db_path.to_string_lossy()) {
+            Ok(conn) => conn,
+            Err(_) => return,
+        };
+        for request in server.incoming_requests() {
+            let response = match request.url() {
+                "/metrics" => match scrape(&conn, &tx) {
+                    Some(text) => Response::from_string(text).with_header(
+                        Header::from_bytes(&b"Content-Type"[..], &b"text/plain; version=0.0.4"[..])
+                            .unwrap(),
+                    ),
+                    None => Response::from_string("timer stopped\n").with_status_code(503),
+                },
+                _ => Response::from_string("see /metrics\n").with_status_code(404),
+            };
+            let _ = request.respond(response);
+        }
+    }))
+}
+
+#[cfg(all(feature = "tui", feature = "sqlite"))]
+fn scrape(conn: &SqliteConnection, tx: &mpsc::Sender<Event>) -> Option<String> {
+    let (reply_tx, reply) = mpsc::channel();
+    tx.send(Event::Command(Command::Status, reply_tx)).ok()?;
+    let status = serde_json::from_str(&reply.recv().ok()?).ok()?;
+    Some(Sample::gather(conn, status).ok()?.render())
+}
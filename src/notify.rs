@@ -0,0 +1,89 @@
+use crate::timer::{State, Timer, Transition};
+use serde::{Deserialize, Serialize};
+use std::io::{self, Write};
+
+/// Escape sequences written to the terminal when a pomodoro or a break ends,
+/// configured in `notifications:`.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy)]
+#[serde(default)]
+pub struct Notifications {
+    pub bell: bool,
+    // desktop notifications of iTerm2, Windows Terminal, kitty and others
+    pub osc9: bool,
+    // desktop notifications of urxvt and VTE terminals
+    pub osc777: bool,
+    // lets the bell mark the window as urgent in xterm
+    pub urgent: bool,
+    // through the session bus, when built with `desktop-notifications`
+    pub desktop: bool,
+}
+
+impl Default for Notifications {
+    fn default() -> Self {
+        Notifications {
+            bell: true,
+            osc9: false,
+            osc777: false,
+            urgent: false,
+            desktop: true,
+        }
+    }
+}
+
+impl Notifications {
+    /// Notifies about the transition if it is one worth noticing.
+    pub fn write<W: Write>(
+        &self,
+        out: &mut W,
+        transition: Transition,
+        timer: &Timer,
+    ) -> io::Result<()> {
+        let (title, body) = match message(transition, timer) {
+            Some(message) => message,
+            None => return Ok(()),
+        };
+        if self.osc9 {
+            write!(out, "\x1b]9;{}: {}\x07", title, sanitize(&body))?;
+        }
+        if self.osc777 {
+            write!(out, "\x1b]777;notify;{};{}\x07", title, sanitize(&body))?;
+        }
+        if self.urgent {
+            write!(out, "\x1b[?1042h")?;
+        }
+        if self.bell || self.urgent {
+            write!(out, "\x07")?;
+        }
+        out.flush()
+    }
+}
+
+/// Title and text of the notification about a transition worth noticing.
+pub fn message(transition: Transition, timer: &Timer) -> Option<(&'static str, String)> {
+    match (transition, timer.state) {
+        (Transition::PomodoroFinish, State::NextBreak(length)) => Some((
+            "Pomodoro finished",
+            format!("Take a {} minute break.", length.as_secs() / 60),
+        )),
+        (Transition::BreakEnd, _) => Some(("Break is over", String::from("Back to work."))),
+        _ => None,
+    }
+}
+
+/// What an attached client, which only sees the state, notifies about.
+pub fn transition_between(old: State, new: State) -> Option<Transition> {
+    match (old, new) {
+        (State::Running, State::NextBreak(_)) => Some(Transition::PomodoroFinish),
+        (State::NextBreak(_), State::Running) => Some(Transition::BreakEnd),
+        _ => None,
+    }
+}
+
+/// Text safe to put in an OSC sequence, control characters and the separator
+/// of OSC 777 would end it.
+pub fn sanitize(text: &str) -> String {
+    text.chars()
+        .filter(|c| !c.is_control())
+        .map(|c| if c == ';' { ',' } else { c })
+        .collect()
+}
@@ -0,0 +1,104 @@
+//! The screens of the terminal interface, drawn on any tui backend so that
+//! tests can look at them.
+
+use crate::config::Cfg;
+use crate::database::HEADER;
+use crate::state::App;
+use std::cmp::max;
+use tui::backend::Backend;
+use tui::layout::{
+    Constraint::{Length, Min, Percentage},
+    Direction::{Horizontal, Vertical},
+    Layout,
+};
+use tui::style::{Color, Modifier, Style};
+use tui::terminal::Frame;
+use tui::widgets::{Block, Borders, Row, Table, Tabs, Widget};
+
+/// Draws the timer, both in the terminal running it and in attached clients,
+/// which say so in the title.
+pub fn draw<B: Backend>(f: &mut Frame<B>, app: &App, cfg: &Cfg, title: &str) {
+    let select_style = Style::default()
+        .bg(Color::Yellow)
+        .fg(Color::Black)
+        .modifier(Modifier::BOLD);
+    let size = f.size();
+    Block::default().render(f, size);
+
+    let chunks = Layout::default()
+        .direction(Vertical)
+        .constraints([Length(3), Min(0)].as_ref())
+        .split(size);
+
+    Tabs::default()
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .titles(app.tabs())
+        .select(app.selected_tab)
+        .highlight_style(select_style)
+        .render(f, chunks[0]);
+    match app.selected_tab {
+        0 => {
+            let chunks_ = Layout::default()
+                .direction(Vertical)
+                .margin(0)
+                .constraints([Percentage(75), Percentage(25)].as_ref())
+                .split(chunks[1]);
+
+            Block::default().borders(Borders::ALL).render(f, chunks_[0]);
+            {
+                let chunks__ = Layout::default()
+                    .direction(Horizontal)
+                    .margin(1)
+                    .constraints([Percentage(50), Percentage(50)].as_ref())
+                    .split(chunks_[0]);
+
+                app.paragraph(f, chunks__[0]);
+                cfg.paragraph(f, chunks__[1]);
+            }
+            {
+                app.gauge(f, chunks_[1]);
+            }
+        }
+        1 => {
+            let rows = app.pomodoros.iter().enumerate().map(|(i, pom)| {
+                let row = pom.as_row();
+                if Some(i) == app.selected_pomodoro {
+                    Row::StyledData(row.into_iter(), select_style)
+                } else {
+                    Row::Data(row.into_iter())
+                }
+            });
+
+            let rects = Layout::default()
+                .constraints(match app.form() {
+                    Some(_) => [Min(0), Length(8)].as_ref(),
+                    None => [Percentage(100)].as_ref(),
+                })
+                .split(chunks[1]);
+            // the note takes what is left after the other columns, the space
+            // between them and the borders. left to a Min constraint, the
+            // layout solver does not share the room the same way every time
+            let fixed = 5 + 19 + 8 + 12 + 6 + (HEADER.len() as u16 - 1) + 2;
+            let note = max(10, rects[0].width.saturating_sub(fixed));
+            Table::new(HEADER.iter(), rows)
+                .block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .title("a: log past pomodoro, t: ticket, n: note, d: delete, u: undo"),
+                )
+                .widths(&[
+                    Length(5),
+                    Length(19),
+                    Length(8),
+                    Length(12),
+                    Length(note),
+                    Length(6),
+                ])
+                .render(f, rects[0]);
+            if let Some(form) = app.form() {
+                form.paragraph(f, rects[1]);
+            }
+        }
+        _ => {}
+    }
+}
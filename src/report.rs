@@ -0,0 +1,151 @@
+use crate::database::{Abandoned, Aggregate, Filter, GroupBy, Pomodoro};
+use chrono::{Duration, NaiveDate, NaiveDateTime};
+use diesel::prelude::*;
+
+/// Layout used when no template file is given, `{{placeholder}}`s are
+/// replaced by the matching parts of the report.
+pub const DEFAULT_TEMPLATE: &str = include_str!("../assets/report.md");
+
+/// Everything that goes into the markdown report of a range of days.
+pub struct Report {
+    pub since: NaiveDate,
+    pub until: NaiveDate,
+    pub days: Vec<Aggregate>,
+    pub tickets: Vec<Aggregate>,
+    pub pomodoros: Vec<Pomodoro>,
+    pub abandoned: i64,
+    // pomodoros separated by at most this much count as one streak
+    pub max_gap: Duration,
+}
+
+impl Report {
+    pub fn gather(
+        conn: &SqliteConnection,
+        since: NaiveDate,
+        until: NaiveDate,
+        max_gap: Duration,
+    ) -> QueryResult<Report> {
+        let filter = Filter {
+            since: Some(since),
+            until: Some(until),
+            ticket: None,
+        };
+        Ok(Report {
+            since,
+            until,
+            days: Aggregate::aggregates(conn, &filter, GroupBy::Day)?,
+            tickets: Aggregate::aggregates(conn, &filter, GroupBy::Ticket)?,
+            pomodoros: Pomodoro::pomodoros_between(conn, since, until)?,
+            abandoned: Abandoned::count_between(conn, since, until)?,
+            max_gap,
+        })
+    }
+
+    pub fn render(&self, template: &str) -> String {
+        template
+            .replace("{{since}}", &self.since.to_string())
+            .replace("{{until}}", &self.until.to_string())
+            .replace("{{total}}", &self.total())
+            .replace("{{days}}", &table("Day", &self.days))
+            .replace("{{tickets}}", &table("Ticket", &self.tickets))
+            .replace("{{streak}}", &self.streak())
+            .replace("{{abandoned}}", &self.abandoned.to_string())
+            .replace("{{notes}}", &self.notes())
+    }
+
+    fn total(&self) -> String {
+        let count: i64 = self.days.iter().map(|agg| agg.count).sum();
+        let duration: i64 = self.days.iter().map(|agg| agg.duration).sum();
+        format!(
+            "**{}** pomodoros, **{}** hours of focus.",
+            count,
+            hours(duration)
+        )
+    }
+
+    fn streak(&self) -> String {
+        match longest_streak(&self.pomodoros, self.max_gap) {
+            Some((count, start, end)) => format!(
+                "{} pomodoros from {} to {}",
+                count,
+                start.format("%Y-%m-%d %H:%M"),
+                end.format("%H:%M")
+            ),
+            None => String::from("none"),
+        }
+    }
+
+    // notes grouped by ticket, in the order the tickets were first worked on
+    fn notes(&self) -> String {
+        let mut tickets: Vec<(Option<&String>, Vec<&String>)> = Vec::new();
+        for pom in &self.pomodoros {
+            let note = match &pom.note {
+                Some(note) => note,
+                None => continue,
+            };
+            let ticket = pom.ticket_id.as_ref();
+            match tickets.iter_mut().find(|(t, _)| *t == ticket) {
+                Some((_, notes)) => notes.push(note),
+                None => tickets.push((ticket, vec![note])),
+            }
+        }
+        if tickets.is_empty() {
+            return String::from("No notes.");
+        }
+        let mut out = String::new();
+        for (ticket, notes) in tickets {
+            out.push_str(&format!(
+                "### {}\n\n",
+                ticket.map(String::as_str).unwrap_or("Without ticket")
+            ));
+            for note in notes {
+                out.push_str(&format!("- {}\n", note));
+            }
+            out.push('\n');
+        }
+        out.trim_end().to_string()
+    }
+}
+
+/// Longest run of pomodoros that each started at most `max_gap` after the
+/// previous one finished, with its start and end.
+pub fn longest_streak(
+    pomodoros: &[Pomodoro],
+    max_gap: Duration,
+) -> Option<(usize, NaiveDateTime, NaiveDateTime)> {
+    let mut best: Option<(usize, NaiveDateTime, NaiveDateTime)> = None;
+    let mut current: Option<(usize, NaiveDateTime, NaiveDateTime)> = None;
+    for pom in pomodoros {
+        let streak = match current {
+            Some((count, start, end)) if pom.started_at() - end <= max_gap => {
+                (count + 1, start, pom.finished_at())
+            }
+            _ => (1, pom.started_at(), pom.finished_at()),
+        };
+        if best.map(|(count, _, _)| count).unwrap_or(0) < streak.0 {
+            best = Some(streak);
+        }
+        current = Some(streak);
+    }
+    best
+}
+
+fn table(label: &str, aggregates: &[Aggregate]) -> String {
+    if aggregates.is_empty() {
+        return String::from("No pomodoros.");
+    }
+    let mut out = format!("| {} | Pomodoros | Time |\n|---|---:|---:|\n", label);
+    for agg in aggregates {
+        out.push_str(&format!(
+            "| {} | {} | {} |\n",
+            agg.group.as_deref().unwrap_or("-"),
+            agg.count,
+            hours(agg.duration)
+        ));
+    }
+    out.trim_end().to_string()
+}
+
+pub fn hours(mins: i64) -> String {
+    format!("{}:{:02}", mins / 60, mins % 60)
+}
@@ -7,5 +7,16 @@ table! {
         duration -> BigInt,
         ticket_id -> Nullable<Text>,
         note -> Nullable<Text>,
+        manual -> Bool,
+        deleted_at -> Nullable<Timestamp>,
+    }
+}
+
+table! {
+    abandoned {
+        id -> Integer,
+        created_at -> Timestamp,
+        duration -> BigInt,
+        ticket_id -> Nullable<Text>,
     }
 }
@@ -1,12 +1,20 @@
-use crate::config::{Cfg, PAUSE_KEY, QUIT_KEY};
-use crate::database::{todays_no_pomodoros, Pomodoro, Statistic};
+use crate::config::{Cfg, ABANDON_KEY, PAUSE_KEY, QUIT_KEY};
+use crate::control::Command;
+use crate::database::{todays_no_pomodoros, Abandoned, Pomodoro, Statistic};
+use crate::events::{Event, EventSource};
+use crate::form::{Form, FormEvent};
+use crate::import::parse_local_timestamp;
+use crate::status::Status;
+use crate::timer::{Record, State, Timer};
 use chrono::naive::NaiveDateTime;
+use chrono::{Local, Utc};
 use failure;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::cmp::min;
 use std::convert::TryFrom;
 use std::time::Duration;
 use std::time::SystemTime;
-use std::cmp::min;
-use std::io::{stdin, BufRead};
 use termion::event::Key;
 use tui::backend::Backend;
 use tui::layout::Rect;
@@ -14,21 +22,41 @@ use tui::style::{Color, Style};
 use tui::terminal::Frame;
 use tui::widgets::{Block, Borders, Gauge, Paragraph, Text, Widget};
 
+/// The timer as the TUI shows it, storing its pomodoros in the database.
+// serialized as the snapshot that attached clients draw
+#[derive(Serialize, Deserialize)]
 pub struct App {
-    pub current_pomodoro: Duration,
-    pub current_break: Duration,
-    pub todays_pomodoros: i64,
+    pub timer: Timer,
     pub pomodoros: Vec<Pomodoro>,
-    pub state: State,
     pub selected_tab: usize,
     pub selected_pomodoro: Option<usize>,
+    // the last hook that failed, until the next transition
+    pub hook_error: Option<String>,
+    // the last webhook delivery or metrics export that failed, until the
+    // next transition
+    pub integration_error: Option<String>,
+    form: Option<(Editing, Form)>,
+    #[serde(skip)]
+    undo: Vec<Undo>,
+    // pomodoros finished since the last `take_finished`, as stored
+    #[serde(skip)]
+    finished: Vec<Pomodoro>,
     tabs: Vec<String>,
 }
 
-pub enum State {
-    Running,
-    NextBreak(Duration),
-    Paused,
+// what the open form is filled in for
+#[derive(Serialize, Deserialize)]
+enum Editing {
+    ManualPomodoro,
+    Ticket(i32),
+    Note(i32),
+}
+
+// changes to recorded pomodoros made in this session, newest last
+enum Undo {
+    Delete(i32),
+    Ticket(i32, Option<String>),
+    Note(i32, Option<String>),
 }
 
 impl App {
@@ -45,105 +73,266 @@ impl App {
         );
         let pomodoros = Pomodoro::pomodoros_of(&cfg.conn, today).unwrap();
         let sel_pom = if pomodoros.is_empty() { None } else { Some(0) };
+        let todays_pomodoros = todays_no_pomodoros(&cfg.conn).unwrap_or(0);
         App {
-            current_break: Duration::from_secs(0),
-            current_pomodoro: Duration::from_secs(0),
-            todays_pomodoros: todays_no_pomodoros(&cfg.conn).unwrap_or(0),
-            pomodoros: pomodoros,
-            state: State::Running,
+            timer: Timer::new(cfg.durations(), todays_pomodoros),
+            pomodoros,
             tabs: vec![String::from("Pomodoro"), String::from("Statistics")],
             selected_tab: 0,
             selected_pomodoro: sel_pom,
+            hook_error: None,
+            integration_error: None,
+            form: None,
+            undo: Vec::new(),
+            finished: Vec::new(),
         }
     }
     pub fn tabs(&self) -> &Vec<String> {
         &self.tabs
     }
+    pub fn form(&self) -> Option<&Form> {
+        self.form.as_ref().map(|(_, form)| form)
+    }
+    fn selected(&self) -> Option<&Pomodoro> {
+        self.selected_pomodoro
+            .and_then(|sel| self.pomodoros.get(sel))
+    }
     // event handlers
 
-    // returns true when to quit
-    pub fn key_handler(&mut self, key: Key) -> bool {
-        match key {
-            Key::Char(PAUSE_KEY) => {
-                self.state = match self.state {
-                    State::Paused => State::Running,
-                    _ => State::Paused,
-                }
+    // returns true when to quit, the caller abandons the pomodoro
+    pub fn key_handler(&mut self, cfg: &Cfg, key: Key) -> Result<bool, failure::Error> {
+        if let Some((_, form)) = self.form.as_mut() {
+            match form.key_handler(key) {
+                Some(FormEvent::Submit) => self.submit_form(cfg)?,
+                Some(FormEvent::Cancel) => self.form = None,
+                None => {}
             }
+            return Ok(false);
+        }
+        match key {
+            Key::Char(PAUSE_KEY) => self.timer.toggle_pause(),
             // Key::Tab => {
             // self.selected_tab = (self.selected_tab + 1) % self.tabs().len();
             // self.state = State::Paused;
             // }
             Key::BackTab => {
                 self.selected_tab = (self.selected_tab + self.tabs.len() - 1) % self.tabs.len();
-                self.state = State::Paused;
-            }
-            Key::Up => {
-                if self.selected_tab == 1 {
-                    match self.selected_pomodoro {
-                        Some(0) => {}
-                        Some(sel) => self.selected_pomodoro = Some(sel - 1),
-                        None => if !self.pomodoros.is_empty() {
-                            self.selected_pomodoro = Some(0);
-                        }
+                self.timer.set_state(State::Paused);
+            }
+            Key::Up if self.selected_tab == 1 => match self.selected_pomodoro {
+                Some(0) => {}
+                Some(sel) => self.selected_pomodoro = Some(sel - 1),
+                None => {
+                    if !self.pomodoros.is_empty() {
+                        self.selected_pomodoro = Some(0);
                     }
                 }
-            }
-            Key::Down => {
-                if self.selected_tab == 1 {
-                    match self.selected_pomodoro {
-                        Some(sel) => self.selected_pomodoro = Some(min(self.pomodoros.len() - 1, sel + 1)),
-                        None => if !self.pomodoros.is_empty() {
-                            self.selected_pomodoro = Some(0);
-                        }
+            },
+            Key::Down if self.selected_tab == 1 => match self.selected_pomodoro {
+                Some(sel) => self.selected_pomodoro = Some(min(self.pomodoros.len() - 1, sel + 1)),
+                None => {
+                    if !self.pomodoros.is_empty() {
+                        self.selected_pomodoro = Some(0);
                     }
                 }
+            },
+            Key::Char('t') if self.selected_tab == 1 => {
+                if let Some(pom) = self.selected() {
+                    let ticket = pom.ticket_id.clone().unwrap_or_default();
+                    let form = Form::new("Edit ticket", vec![("Ticket", ticket)]);
+                    self.form = Some((Editing::Ticket(pom.id), form));
+                }
             }
-            Key::Char('t') => {
-                if self.selected_tab == 1 {
-                    println!("Editing ticket text!");
-                    let mut input = String::new();
-                    let stdin = stdin();
-                    let mut handle = stdin.lock();
-                    handle.read_line(&mut input).unwrap();
-                    println!("You typed: {}", input.trim());
+            Key::Char('n') if self.selected_tab == 1 => {
+                if let Some(pom) = self.selected() {
+                    let note = pom.note.clone().unwrap_or_default();
+                    let form = Form::new("Edit note", vec![("Note", note)]);
+                    self.form = Some((Editing::Note(pom.id), form));
                 }
             }
-            Key::Char('n') => {
-                if self.selected_tab == 1 {
-                    println!("Editing note text!")
+            Key::Char('a') if self.selected_tab == 1 => {
+                let now = Local::now().naive_local() - chrono::Duration::from_std(cfg.working)?;
+                let form = Form::new(
+                    "Log a past pomodoro",
+                    vec![
+                        ("Start", now.format("%Y-%m-%d %H:%M").to_string()),
+                        ("Minutes", format!("{}", cfg.working.as_secs() / 60)),
+                        ("Ticket", String::new()),
+                        ("Note", String::new()),
+                    ],
+                );
+                self.form = Some((Editing::ManualPomodoro, form));
+            }
+            Key::Char('d') if self.selected_tab == 1 => {
+                if let Some(id) = self.selected().map(|pom| pom.id) {
+                    Statistic::delete(&cfg.conn, id)?;
+                    self.undo.push(Undo::Delete(id));
+                    self.reload(cfg)?;
                 }
             }
+            Key::Char('u') if self.selected_tab == 1 => {
+                match self.undo.pop() {
+                    Some(Undo::Delete(id)) => Statistic::restore(&cfg.conn, id)?,
+                    Some(Undo::Ticket(id, ticket)) => Statistic::set_ticket(&cfg.conn, id, ticket)?,
+                    Some(Undo::Note(id, note)) => Statistic::set_note(&cfg.conn, id, note)?,
+                    None => 0,
+                };
+                self.reload(cfg)?;
+            }
+            Key::Char(ABANDON_KEY) if self.selected_tab == 0 => self.abandon(cfg)?,
+            Key::Char(QUIT_KEY) => return Ok(true),
             _ => {}
         };
-        key == Key::Char(QUIT_KEY)
+        Ok(false)
+    }
+
+    // records the unfinished pomodoro and starts over
+    pub fn abandon(&mut self, cfg: &Cfg) -> Result<(), failure::Error> {
+        let record = self.timer.abandon()?;
+        self.record(cfg, record)?;
+        Ok(())
+    }
+
+    // abandons the pomodoro without starting the next one
+    pub fn quit(&mut self, cfg: &Cfg) -> Result<(), failure::Error> {
+        let record = self.timer.quit()?;
+        self.record(cfg, record)?;
+        Ok(())
+    }
+
+    // returns the row of a finished pomodoro
+    fn record(
+        &mut self,
+        cfg: &Cfg,
+        record: Option<Record>,
+    ) -> Result<Option<Pomodoro>, failure::Error> {
+        match record {
+            Some(Record::Finished { minutes, ticket }) => {
+                let mut stat = Statistic::new(minutes);
+                stat.ticket_id = ticket;
+                return Ok(Some(stat.insert_row(&cfg.conn)?));
+            }
+            Some(Record::Abandoned { minutes, ticket }) => {
+                Abandoned {
+                    duration: minutes,
+                    ticket_id: ticket,
+                }
+                .insert(&cfg.conn)?;
+            }
+            None => {}
+        }
+        Ok(None)
+    }
+
+    fn submit_form(&mut self, cfg: &Cfg) -> Result<(), failure::Error> {
+        let (editing, form) = match self.form.as_mut() {
+            Some(form) => form,
+            None => return Ok(()),
+        };
+        let text = |field: usize| Some(String::from(form.value(field))).filter(|t| !t.is_empty());
+        match *editing {
+            Editing::ManualPomodoro => return self.submit_manual_pomodoro(cfg),
+            Editing::Ticket(id) => {
+                let previous = self.pomodoros.iter().find(|pom| pom.id == id);
+                let previous = previous.and_then(|pom| pom.ticket_id.clone());
+                Statistic::set_ticket(&cfg.conn, id, text(0))?;
+                self.undo.push(Undo::Ticket(id, previous));
+            }
+            Editing::Note(id) => {
+                let previous = self.pomodoros.iter().find(|pom| pom.id == id);
+                let previous = previous.and_then(|pom| pom.note.clone());
+                Statistic::set_note(&cfg.conn, id, text(0))?;
+                self.undo.push(Undo::Note(id, previous));
+            }
+        }
+        self.form = None;
+        self.reload(cfg)
+    }
+
+    // keeps the form open with an error message when the input is invalid
+    fn submit_manual_pomodoro(&mut self, cfg: &Cfg) -> Result<(), failure::Error> {
+        let form = match self.form.as_mut() {
+            Some((_, form)) => form,
+            None => return Ok(()),
+        };
+        let start = match parse_local_timestamp(form.value(0)) {
+            Ok(start) => start,
+            Err(e) => {
+                form.error = Some(e.to_string());
+                return Ok(());
+            }
+        };
+        let duration = match form.value(1).parse::<i64>() {
+            Ok(duration) if duration > 0 => duration,
+            _ => {
+                form.error = Some(String::from("Minutes must be a positive number."));
+                return Ok(());
+            }
+        };
+        let text = |field: usize| Some(String::from(form.value(field))).filter(|t| !t.is_empty());
+        Statistic::manual(start, duration, text(2), text(3)).insert(&cfg.conn)?;
+        self.form = None;
+        self.reload(cfg)
+    }
+
+    pub fn reload(&mut self, cfg: &Cfg) -> Result<(), failure::Error> {
+        self.pomodoros = Pomodoro::pomodoros_of(&cfg.conn, Utc::now().naive_utc())?;
+        self.timer.todays_pomodoros = todays_no_pomodoros(&cfg.conn)?;
+        self.selected_pomodoro = match self.selected_pomodoro {
+            _ if self.pomodoros.is_empty() => None,
+            Some(sel) => Some(min(self.pomodoros.len() - 1, sel)),
+            None => Some(0),
+        };
+        Ok(())
     }
     pub fn tick(&mut self, cfg: &Cfg, duration: Duration) -> Result<(), failure::Error> {
-        match self.state {
-            State::Running => {
-                self.current_pomodoro = self.current_pomodoro + duration;
-                if cfg.working <= self.current_pomodoro {
-                    let working_mins: i64 = TryFrom::try_from(cfg.working.as_secs() / 60)?;
-                    Statistic::new(working_mins).insert(&cfg.conn)?;
-                    self.todays_pomodoros += 1;
-                    self.current_pomodoro = Duration::from_secs(0);
-                    if self.todays_pomodoros % 4 == 0 {
-                        self.state = State::NextBreak(cfg.long_break);
-                    } else {
-                        self.state = State::NextBreak(cfg.short_break);
-                    }
+        let record = self.timer.tick(duration)?;
+        if let Some(pom) = self.record(cfg, record)? {
+            self.finished.push(pom);
+        }
+        Ok(())
+    }
+
+    /// The pomodoros finished since the last call, as they were stored.
+    pub fn take_finished(&mut self) -> Vec<Pomodoro> {
+        std::mem::take(&mut self.finished)
+    }
+
+    /// Handles a command of the control socket and returns the answer.
+    pub fn command_handler(
+        &mut self,
+        cfg: &Cfg,
+        command: Command,
+    ) -> Result<String, failure::Error> {
+        match command {
+            Command::Pause => self.timer.set_state(State::Paused),
+            Command::Resume => self.timer.resume(),
+            Command::Skip => {
+                if !self.timer.skip() {
+                    return Ok(String::from("error: only breaks can be skipped"));
                 }
             }
-            State::NextBreak(next_break) => {
-                self.current_break = self.current_break + duration;
-                if next_break <= self.current_break {
-                    self.current_break = Duration::from_secs(0);
-                    self.state = State::Running
+            Command::Abandon => self.abandon(cfg)?,
+            Command::SetTicket(ticket) => self.timer.ticket = ticket,
+            Command::Status => return Ok(serde_json::to_string(&Status::of(&self.timer))?),
+            Command::Key(key) => {
+                if self.key_handler(cfg, key)? {
+                    return Ok(String::from("error: send stop to quit the timer"));
                 }
             }
-            State::Paused => {}
+            Command::Snapshot => {
+                let snapshot = json!({
+                    "app": self,
+                    "working": cfg.working,
+                    "short_break": cfg.short_break,
+                    "long_break": cfg.long_break,
+                });
+                return Ok(snapshot.to_string());
+            }
+            // the event loop quits after answering
+            Command::Stop => self.quit(cfg)?,
         }
-        Ok(())
+        Ok(String::from("ok"))
     }
 
     // render functions
@@ -151,12 +340,32 @@ impl App {
     where
         B: Backend,
     {
-        let content = [
-            Text::raw(format!("Past pomodoros: {}\n", self.todays_pomodoros)),
+        let mut content = vec![
+            Text::raw(format!("Past pomodoros: {}\n", self.timer.todays_pomodoros)),
+            Text::raw(format!(
+                "Ticket: {}\n",
+                self.timer.ticket.as_deref().unwrap_or("none")
+            )),
             Text::raw(format!("Press '{}' to toggle pause.\n", PAUSE_KEY)),
+            Text::raw(format!(
+                "Press '{}' to abandon the pomodoro.\n",
+                ABANDON_KEY
+            )),
             Text::raw("Toggle pause to skip break.\n"),
             Text::raw(format!("Press '{}' to quit.", QUIT_KEY)),
         ];
+        if let Some(error) = &self.hook_error {
+            content.push(Text::styled(
+                format!("\nHook failed: {}", error),
+                Style::default().fg(Color::Red),
+            ));
+        }
+        if let Some(error) = &self.integration_error {
+            content.push(Text::styled(
+                format!("\nIntegration failed: {}", error),
+                Style::default().fg(Color::Red),
+            ));
+        }
         Paragraph::new(content.iter())
             .block(
                 Block::default()
@@ -166,34 +375,25 @@ impl App {
             .render(f, area);
     }
 
-    pub fn gauge<B>(&self, cfg: &Cfg, f: &mut Frame<B>, area: Rect)
+    pub fn gauge<B>(&self, f: &mut Frame<B>, area: Rect)
     where
         B: Backend,
     {
+        let timer = &self.timer;
         Gauge::default()
             .block(Block::default().title("Pomodoro").borders(Borders::ALL))
-            .ratio(match self.state {
-                State::Running => self.current_pomodoro.as_secs_f64() / cfg.working.as_secs_f64(),
-                State::NextBreak(break_duration) => {
-                    self.current_break.as_secs_f64() / break_duration.as_secs_f64()
-                }
-                State::Paused => 1.0,
-            })
+            .ratio(timer.progress())
             .label(
-                match self.state {
-                    State::Running => {
-                        let remaining_time = (cfg.working - self.current_pomodoro).as_secs();
-                        format!("{:02}:{:02}", remaining_time / 60, remaining_time % 60)
-                    }
-                    State::NextBreak(break_duration) => {
-                        let remaining_time = (break_duration - self.current_break).as_secs();
+                match timer.state {
+                    State::Paused => String::from("Paused"),
+                    _ => {
+                        let remaining_time = timer.remaining().as_secs();
                         format!("{:02}:{:02}", remaining_time / 60, remaining_time % 60)
                     }
-                    State::Paused => String::from("Paused"),
                 }
                 .as_ref(),
             )
-            .style(Style::default().fg(match self.state {
+            .style(Style::default().fg(match timer.state {
                 State::Running => Color::Blue,
                 State::Paused => Color::Red,
                 State::NextBreak(_) => Color::Gray,
@@ -201,3 +401,60 @@ impl App {
             .render(f, area);
     }
 }
+
+/// The event loop of a timer: handles the keys, ticks and commands of
+/// `events` until the timer quits or they run dry, and returns whether it
+/// quit. `react` runs at the start and after every event, to draw the timer
+/// and react to its transitions. Commands are answered after that.
+pub fn run_timer<S, F>(
+    app: &mut App,
+    cfg: &Cfg,
+    events: &mut S,
+    mut react: F,
+) -> Result<bool, failure::Error>
+where
+    S: EventSource,
+    F: FnMut(&mut App) -> Result<(), failure::Error>,
+{
+    react(app)?;
+    loop {
+        let event = match events.next() {
+            Ok(event) => event,
+            Err(_) => return Ok(false),
+        };
+        let mut reply = None;
+        let quit = match event {
+            Event::Input(key) => {
+                let quit = app.key_handler(cfg, key)?;
+                if quit {
+                    app.quit(cfg)?;
+                }
+                quit
+            }
+            Event::Tick(duration) => {
+                app.tick(cfg, duration)?;
+                false
+            }
+            Event::Command(command, sender) => {
+                let quit = command == Command::Stop;
+                let answer = app
+                    .command_handler(cfg, command)
+                    .unwrap_or_else(|e| format!("error: {}", e));
+                reply = Some((sender, answer));
+                quit
+            }
+            Event::HookFailed(error) => {
+                app.hook_error = Some(error);
+                false
+            }
+        };
+        react(app)?;
+        // clients may read the status right after the answer
+        if let Some((sender, answer)) = reply {
+            let _ = sender.send(answer);
+        }
+        if quit {
+            return Ok(true);
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use crate::config::runtime_dir;
+use crate::timer::Timer;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// Written by the running timer so that `pomodorust status` can show it
+/// without talking to the TUI.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct Status {
+    pub state: String,
+    pub remaining_secs: u64,
+    pub count: i64,
+    pub ticket: Option<String>,
+    pub updated_at: u64,
+}
+
+pub const DEFAULT_FORMAT: &str = "{state} {remaining} {count}";
+
+// a timer that has not written for this long is gone
+const STALE_AFTER: Duration = Duration::from_secs(10);
+
+pub fn status_path() -> io::Result<PathBuf> {
+    Ok(runtime_dir()?.join("status.json"))
+}
+
+impl Status {
+    pub fn of(timer: &Timer) -> Status {
+        Status {
+            state: String::from(timer.state.name()),
+            remaining_secs: timer.remaining().as_secs(),
+            count: timer.todays_pomodoros,
+            ticket: timer.ticket.clone(),
+            updated_at: unix_now(),
+        }
+    }
+
+    pub fn stopped() -> Status {
+        Status {
+            state: String::from("stopped"),
+            remaining_secs: 0,
+            count: 0,
+            ticket: None,
+            updated_at: unix_now(),
+        }
+    }
+
+    // written to a temporary file first so readers never see half of it
+    pub fn write(&self) -> io::Result<()> {
+        let path = status_path()?;
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_vec(self)?)?;
+        fs::rename(tmp, path)
+    }
+
+    pub fn remove() -> io::Result<()> {
+        match fs::remove_file(status_path()?) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    /// The last status written by a timer, or `stopped` if none is running.
+    pub fn read() -> Status {
+        match status_path() {
+            Ok(path) => Status::read_from(&path),
+            Err(_) => Status::stopped(),
+        }
+    }
+
+    /// The status written to `path`, or `stopped` if it is missing or stale.
+    pub fn read_from(path: &Path) -> Status {
+        fs::read(path)
+            .ok()
+            .and_then(|json| serde_json::from_slice::<Status>(&json).ok())
+            .filter(|status| unix_now() < status.updated_at + STALE_AFTER.as_secs())
+            .unwrap_or_else(Status::stopped)
+    }
+
+    pub fn remaining(&self) -> String {
+        if self.state == "stopped" {
+            return String::from("--:--");
+        }
+        format!(
+            "{:02}:{:02}",
+            self.remaining_secs / 60,
+            self.remaining_secs % 60
+        )
+    }
+
+    /// Replaces `{state}`, `{remaining}`, `{count}` and `{ticket}`.
+    pub fn format(&self, format: &str) -> String {
+        format
+            .replace("{state}", &self.state)
+            .replace("{remaining}", &self.remaining())
+            .replace("{count}", &self.count.to_string())
+            .replace("{ticket}", self.ticket.as_deref().unwrap_or_default())
+            .trim()
+            .to_string()
+    }
+
+    /// A block of the i3bar protocol, as used by i3blocks and friends.
+    pub fn i3bar(&self, format: &str) -> String {
+        json!({
+            "full_text": self.format(format),
+            "short_text": self.remaining(),
+            "color": match self.state.as_str() {
+                "running" => "#5f87d7",
+                "break" => "#87af87",
+                "paused" => "#d75f5f",
+                _ => "#808080",
+            },
+        })
+        .to_string()
+    }
+
+    /// Output of a waybar custom module with `return-type` json.
+    pub fn waybar(&self, format: &str) -> String {
+        json!({
+            "text": self.format(format),
+            "alt": self.state,
+            "class": self.state,
+            "tooltip": format!("{} pomodoros today", self.count),
+        })
+        .to_string()
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
@@ -0,0 +1,273 @@
+//! The pomodoro timer itself, without a user interface or storage. It is
+//! driven by `tick` and the commands of its owner, reports what happened as
+//! transitions and hands the pomodoros to record back as `Record`s.
+
+use failure::{self, bail};
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// How long pomodoros and breaks last.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct Durations {
+    pub working: Duration,
+    pub short_break: Duration,
+    pub long_break: Duration,
+}
+
+impl Default for Durations {
+    fn default() -> Self {
+        Durations {
+            working: Duration::from_secs(25 * 60),
+            short_break: Duration::from_secs(5 * 60),
+            long_break: Duration::from_secs(10 * 60),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum State {
+    Running,
+    NextBreak(Duration),
+    Paused,
+}
+
+impl State {
+    pub fn name(self) -> &'static str {
+        match self {
+            State::Running => "running",
+            State::NextBreak(_) => "break",
+            State::Paused => "paused",
+        }
+    }
+}
+
+/// Changes of the timer that hooks and notifications react to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Transition {
+    PomodoroStart,
+    PomodoroFinish,
+    BreakStart,
+    BreakEnd,
+    Pause,
+    Resume,
+    Abandon,
+}
+
+pub const TRANSITIONS: [Transition; 7] = [
+    Transition::PomodoroStart,
+    Transition::PomodoroFinish,
+    Transition::BreakStart,
+    Transition::BreakEnd,
+    Transition::Pause,
+    Transition::Resume,
+    Transition::Abandon,
+];
+
+impl Transition {
+    pub fn name(self) -> &'static str {
+        match self {
+            Transition::PomodoroStart => "pomodoro_start",
+            Transition::PomodoroFinish => "pomodoro_finish",
+            Transition::BreakStart => "break_start",
+            Transition::BreakEnd => "break_end",
+            Transition::Pause => "pause",
+            Transition::Resume => "resume",
+            Transition::Abandon => "abandon",
+        }
+    }
+}
+
+impl FromStr for Transition {
+    type Err = failure::Error;
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match TRANSITIONS.iter().find(|t| t.name() == name) {
+            Some(transition) => Ok(*transition),
+            None => bail!("unknown transition '{}'", name),
+        }
+    }
+}
+
+/// A pomodoro the owner of the timer should store, in whole minutes.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Record {
+    Finished {
+        minutes: i64,
+        ticket: Option<String>,
+    },
+    Abandoned {
+        minutes: i64,
+        ticket: Option<String>,
+    },
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Timer {
+    pub durations: Durations,
+    pub current_pomodoro: Duration,
+    pub current_break: Duration,
+    pub todays_pomodoros: i64,
+    pub state: State,
+    // recorded with the next finished pomodoro
+    pub ticket: Option<String>,
+    // since the owner last looked, with the length of the pomodoro or break
+    // they belong to
+    #[serde(skip)]
+    transitions: Vec<(Transition, Duration)>,
+}
+
+impl Timer {
+    /// A running timer, the count decides when the long break comes.
+    pub fn new(durations: Durations, todays_pomodoros: i64) -> Timer {
+        Timer {
+            durations,
+            current_pomodoro: Duration::from_secs(0),
+            current_break: Duration::from_secs(0),
+            todays_pomodoros,
+            state: State::Running,
+            ticket: None,
+            transitions: vec![(Transition::PomodoroStart, durations.working)],
+        }
+    }
+
+    /// The transitions since the last call, oldest first.
+    pub fn take_transitions(&mut self) -> Vec<(Transition, Duration)> {
+        std::mem::take(&mut self.transitions)
+    }
+
+    // records the transitions between the states, a pomodoro starts whenever
+    // the timer runs with nothing worked yet
+    pub fn set_state(&mut self, state: State) {
+        let mut transitions = match (self.state, state) {
+            (State::Paused, State::Paused) => vec![],
+            (_, State::Paused) => vec![Transition::Pause],
+            (State::Paused, State::Running) => vec![Transition::Resume],
+            (State::Running, State::NextBreak(_)) => vec![Transition::BreakStart],
+            (State::NextBreak(_), State::Running) => vec![Transition::BreakEnd],
+            _ => vec![],
+        };
+        if state == State::Running
+            && self.state != State::Running
+            && self.current_pomodoro.as_secs() == 0
+        {
+            transitions.push(Transition::PomodoroStart);
+        }
+        for transition in transitions {
+            let length = match (transition, self.state, state) {
+                (Transition::BreakEnd, State::NextBreak(length), _) => length,
+                (_, _, State::NextBreak(length)) => length,
+                _ => self.durations.working,
+            };
+            self.transitions.push((transition, length));
+        }
+        self.state = state;
+    }
+
+    pub fn toggle_pause(&mut self) {
+        let state = match self.state {
+            State::Paused => State::Running,
+            _ => State::Paused,
+        };
+        self.set_state(state)
+    }
+
+    pub fn resume(&mut self) {
+        if let State::Paused = self.state {
+            self.set_state(State::Running);
+        }
+    }
+
+    // returns false while a pomodoro runs, only breaks can be skipped
+    pub fn skip(&mut self) -> bool {
+        if self.state == State::Running {
+            return false;
+        }
+        self.current_break = Duration::from_secs(0);
+        self.set_state(State::Running);
+        true
+    }
+
+    /// Advances the timer, a finished pomodoro comes back to be recorded.
+    pub fn tick(&mut self, duration: Duration) -> Result<Option<Record>, failure::Error> {
+        match self.state {
+            State::Running => {
+                self.current_pomodoro += duration;
+                if self.durations.working <= self.current_pomodoro {
+                    let minutes = i64::try_from(self.durations.working.as_secs() / 60)?;
+                    self.todays_pomodoros += 1;
+                    self.current_pomodoro = Duration::from_secs(0);
+                    self.transitions
+                        .push((Transition::PomodoroFinish, self.durations.working));
+                    if self.todays_pomodoros % 4 == 0 {
+                        self.set_state(State::NextBreak(self.durations.long_break));
+                    } else {
+                        self.set_state(State::NextBreak(self.durations.short_break));
+                    }
+                    return Ok(Some(Record::Finished {
+                        minutes,
+                        ticket: self.ticket.clone(),
+                    }));
+                }
+            }
+            State::NextBreak(next_break) => {
+                self.current_break += duration;
+                if next_break <= self.current_break {
+                    self.current_break = Duration::from_secs(0);
+                    self.set_state(State::Running)
+                }
+            }
+            State::Paused => {}
+        }
+        Ok(None)
+    }
+
+    // starts the pomodoro over, less than a minute of work is not worth
+    // recording
+    pub fn abandon(&mut self) -> Result<Option<Record>, failure::Error> {
+        let minutes = i64::try_from(self.current_pomodoro.as_secs() / 60)?;
+        self.current_pomodoro = Duration::from_secs(0);
+        self.transitions
+            .push((Transition::Abandon, self.durations.working));
+        if self.state == State::Running {
+            self.transitions
+                .push((Transition::PomodoroStart, self.durations.working));
+        }
+        if minutes == 0 {
+            return Ok(None);
+        }
+        Ok(Some(Record::Abandoned {
+            minutes,
+            ticket: self.ticket.clone(),
+        }))
+    }
+
+    // abandons the pomodoro without starting the next one
+    pub fn quit(&mut self) -> Result<Option<Record>, failure::Error> {
+        let record = self.abandon()?;
+        self.transitions
+            .retain(|(transition, _)| *transition != Transition::PomodoroStart);
+        Ok(record)
+    }
+
+    // a paused timer continues with the pomodoro
+    pub fn remaining(&self) -> Duration {
+        match self.state {
+            State::NextBreak(break_duration) => break_duration.saturating_sub(self.current_break),
+            _ => self.durations.working.saturating_sub(self.current_pomodoro),
+        }
+    }
+
+    /// How much of the pomodoro or break is over, between 0 and 1.
+    pub fn progress(&self) -> f64 {
+        match self.state {
+            State::Running => {
+                self.current_pomodoro.as_secs_f64() / self.durations.working.as_secs_f64()
+            }
+            State::NextBreak(break_duration) => {
+                self.current_break.as_secs_f64() / break_duration.as_secs_f64()
+            }
+            State::Paused => 1.0,
+        }
+    }
+}
@@ -0,0 +1,178 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::cmp::min;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+// waited between retries at most, the wait doubles up to it
+const MAX_BACKOFF: Duration = Duration::from_secs(15 * 60);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Delivery {
+    pub id: u128,
+    pub url: String,
+    pub payload: Value,
+}
+
+/// POSTs completed pomodoros to the configured `webhooks:` URLs. Deliveries
+/// are queued on disk first and sent from a thread of their own, those that
+/// fail are retried, also after a restart, until the endpoint takes them.
+pub struct Webhooks {
+    urls: Vec<String>,
+    queue: Arc<Mutex<Queue>>,
+    wake: mpsc::Sender<()>,
+}
+
+struct Queue {
+    path: PathBuf,
+    deliveries: Vec<Delivery>,
+}
+
+pub fn queue_path(db_path: &Path) -> PathBuf {
+    let mut name = db_path.as_os_str().to_owned();
+    name.push(".webhooks.json");
+    PathBuf::from(name)
+}
+
+impl Queue {
+    fn save(&self) -> io::Result<()> {
+        if self.deliveries.is_empty() {
+            return match fs::remove_file(&self.path) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+                _ => Ok(()),
+            };
+        }
+        let tmp = self.path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_vec_pretty(&self.deliveries)?)?;
+        fs::rename(tmp, &self.path)
+    }
+}
+
+impl Webhooks {
+    /// None without URLs. `retry` is the first wait after a failed delivery.
+    pub fn start(urls: &[String], queue_path: PathBuf, retry: Duration) -> Option<Webhooks> {
+        if urls.is_empty() {
+            return None;
+        }
+        // left over from the last run
+        let deliveries: Vec<Delivery> = fs::read(&queue_path)
+            .ok()
+            .and_then(|json| serde_json::from_slice(&json).ok())
+            .unwrap_or_default();
+        // right away for those, otherwise once something is sent
+        let first = if deliveries.is_empty() {
+            None
+        } else {
+            Some(Duration::from_secs(0))
+        };
+        let queue = Arc::new(Mutex::new(Queue {
+            path: queue_path,
+            deliveries,
+        }));
+        let (wake, woken) = mpsc::channel();
+        {
+            let queue = queue.clone();
+            thread::spawn(move || deliver(&queue, woken, first, retry));
+        }
+        Some(Webhooks {
+            urls: urls.to_vec(),
+            queue,
+            wake,
+        })
+    }
+
+    pub fn send(&self, payload: &Value) -> io::Result<()> {
+        {
+            let mut queue = self.queue.lock().unwrap();
+            for url in &self.urls {
+                let delivery = Delivery {
+                    id: new_id(),
+                    url: url.clone(),
+                    payload: payload.clone(),
+                };
+                queue.deliveries.push(delivery);
+            }
+            queue.save()?;
+        }
+        let _ = self.wake.send(());
+        Ok(())
+    }
+
+    pub fn pending(&self) -> Vec<Delivery> {
+        self.queue.lock().unwrap().deliveries.clone()
+    }
+}
+
+// unique within the queue, which only this process writes: the time tells
+// apart runs, the count ids taken within the same nanosecond
+fn new_id() -> u128 {
+    static COUNT: AtomicU64 = AtomicU64::new(0);
+    let nanos = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+    nanos << 64 | u128::from(COUNT.fetch_add(1, Ordering::Relaxed))
+}
+
+// tries whatever is queued whenever something new comes in or the backoff is
+// over, the lock is never held while talking to the network
+fn deliver(
+    queue: &Mutex<Queue>,
+    woken: mpsc::Receiver<()>,
+    first: Option<Duration>,
+    retry: Duration,
+) {
+    let mut backoff = retry;
+    let mut wait = first;
+    loop {
+        let gone = match wait {
+            Some(wait) => woken.recv_timeout(wait) == Err(RecvTimeoutError::Disconnected),
+            None => woken.recv().is_err(),
+        };
+        if gone {
+            return;
+        }
+        // one attempt covers everything sent so far
+        while woken.try_recv().is_ok() {}
+        let pending = queue.lock().unwrap().deliveries.clone();
+        let done: Vec<u128> = pending
+            .iter()
+            .filter(|delivery| post(delivery))
+            .map(|delivery| delivery.id)
+            .collect();
+        let mut queue = queue.lock().unwrap();
+        queue
+            .deliveries
+            .retain(|delivery| !done.contains(&delivery.id));
+        let _ = queue.save();
+        if queue.deliveries.is_empty() {
+            backoff = retry;
+            wait = None;
+        } else {
+            wait = Some(backoff);
+            backoff = min(backoff * 2, MAX_BACKOFF);
+        }
+    }
+}
+
+// true once the delivery is done with, a client error will not go away by
+// sending the same again unless the endpoint asked to come back later
+fn post(delivery: &Delivery) -> bool {
+    let sent = ureq::post(&delivery.url)
+        .timeout(REQUEST_TIMEOUT)
+        .set("Content-Type", "application/json")
+        .send_string(&delivery.payload.to_string());
+    match sent {
+        Ok(_) => true,
+        Err(ureq::Error::Status(408, _)) | Err(ureq::Error::Status(429, _)) => false,
+        Err(ureq::Error::Status(status, _)) => (400..500).contains(&status),
+        Err(_) => false,
+    }
+}
@@ -0,0 +1,315 @@
+//! The TUI application driven by scripted keys on a fake clock, recording
+//! into an in-memory database.
+
+use chrono::{Duration as Days, Utc};
+use pomodorust::clock::{Clock, FakeClock};
+use pomodorust::config::{Cfg, ABANDON_KEY, PAUSE_KEY, QUIT_KEY};
+use pomodorust::control::Command;
+use pomodorust::database::{todays_no_pomodoros, Abandoned, Pomodoro, Statistic};
+use pomodorust::events::{Event, EventSource, Script};
+use pomodorust::state::{run_timer, App};
+use pomodorust::timer::{Durations, State, Transition};
+use std::sync::mpsc;
+use std::time::Duration;
+use termion::event::Key;
+
+fn mins(mins: u64) -> Duration {
+    Duration::from_secs(mins * 60)
+}
+
+fn cfg() -> Cfg {
+    Cfg::in_memory(Durations::default()).unwrap()
+}
+
+// the event loop without a terminal, returns whether it quit
+fn run<S: EventSource>(app: &mut App, cfg: &Cfg, mut events: S) -> bool {
+    run_timer(app, cfg, &mut events, |_| Ok(())).unwrap()
+}
+
+fn transitions(app: &mut App) -> Vec<Transition> {
+    app.timer
+        .take_transitions()
+        .into_iter()
+        .map(|(transition, _)| transition)
+        .collect()
+}
+
+#[test]
+fn a_day_of_pomodoros_is_recorded() {
+    let cfg = cfg();
+    let mut app = App::new(&cfg);
+    let clock = FakeClock::default();
+    let mut script = Script::new(clock.clone());
+    // three short breaks and a long one
+    for _ in 0..3 {
+        script = script.wait(mins(30));
+    }
+    run(&mut app, &cfg, script.wait(mins(35)));
+
+    assert_eq!(clock.now(), mins(125));
+    assert_eq!(todays_no_pomodoros(&cfg.conn).unwrap(), 4);
+    assert_eq!(app.timer.todays_pomodoros, 4);
+    assert_eq!(app.timer.state, State::Running);
+    let finished = transitions(&mut app)
+        .into_iter()
+        .filter(|t| *t == Transition::PomodoroFinish)
+        .count();
+    assert_eq!(finished, 4);
+
+    let pomodoros = Pomodoro::pomodoros_of(&cfg.conn, Utc::now().naive_utc()).unwrap();
+    assert_eq!(pomodoros.len(), 4);
+    assert!(pomodoros
+        .iter()
+        .all(|pom| pom.duration == 25 && !pom.manual));
+    // the rows as stored, for the webhooks
+    assert_eq!(app.take_finished(), pomodoros);
+}
+
+#[test]
+fn the_fourth_pomodoro_is_followed_by_the_long_break() {
+    let cfg = cfg();
+    let mut app = App::new(&cfg);
+    let script = Script::new(FakeClock::default())
+        .wait(mins(30 * 3))
+        .wait(mins(25));
+    run(&mut app, &cfg, script);
+    assert_eq!(app.timer.state, State::NextBreak(mins(10)));
+    assert_eq!(app.timer.remaining(), mins(10));
+}
+
+#[test]
+fn pausing_stops_the_clock_of_the_pomodoro() {
+    let cfg = cfg();
+    let mut app = App::new(&cfg);
+    let script = Script::new(FakeClock::default())
+        .wait(mins(10))
+        .key(Key::Char(PAUSE_KEY))
+        .wait(mins(60))
+        .key(Key::Char(PAUSE_KEY))
+        .wait(mins(5));
+    run(&mut app, &cfg, script);
+    assert_eq!(app.timer.state, State::Running);
+    assert_eq!(app.timer.remaining(), mins(10));
+    assert_eq!(todays_no_pomodoros(&cfg.conn).unwrap(), 0);
+}
+
+#[test]
+fn pausing_a_break_skips_it() {
+    let cfg = cfg();
+    let mut app = App::new(&cfg);
+    let script = Script::new(FakeClock::default())
+        .wait(mins(26))
+        .key(Key::Char(PAUSE_KEY))
+        .key(Key::Char(PAUSE_KEY));
+    run(&mut app, &cfg, script);
+    assert_eq!(app.timer.state, State::Running);
+    assert_eq!(app.timer.remaining(), mins(25));
+}
+
+#[test]
+fn abandoned_pomodoros_are_kept_apart() {
+    let cfg = cfg();
+    let mut app = App::new(&cfg);
+    app.timer.take_transitions();
+    let script = Script::new(FakeClock::default())
+        .wait(mins(12))
+        .key(Key::Char(ABANDON_KEY))
+        .wait(mins(25));
+    run(&mut app, &cfg, script);
+
+    let today = Utc::today().naive_utc();
+    let abandoned = Abandoned::count_between(&cfg.conn, today - Days::days(1), today);
+    assert_eq!(abandoned.unwrap(), 1);
+    assert_eq!(todays_no_pomodoros(&cfg.conn).unwrap(), 1);
+    assert_eq!(
+        transitions(&mut app),
+        vec![
+            Transition::Abandon,
+            Transition::PomodoroStart,
+            Transition::PomodoroFinish,
+            Transition::BreakStart,
+        ]
+    );
+}
+
+#[test]
+fn quitting_abandons_the_pomodoro() {
+    let cfg = cfg();
+    let mut app = App::new(&cfg);
+    app.timer.take_transitions();
+    let script = Script::new(FakeClock::default())
+        .wait(mins(3))
+        .keys(&format!("{}{}", QUIT_KEY, PAUSE_KEY));
+    assert!(run(&mut app, &cfg, script));
+    assert_eq!(transitions(&mut app), vec![Transition::Abandon]);
+}
+
+#[test]
+fn commands_are_answered() {
+    let cfg = cfg();
+    let mut app = App::new(&cfg);
+    let (reply, answers) = mpsc::channel();
+    let script = Script::new(FakeClock::default())
+        .wait(mins(5))
+        .command(Command::Skip, reply.clone())
+        .command(Command::Pause, reply.clone())
+        .command(Command::Status, reply.clone())
+        .command(Command::Resume, reply);
+    assert!(!run(&mut app, &cfg, script));
+    let answers: Vec<String> = answers.iter().collect();
+    assert_eq!(answers[0], "error: only breaks can be skipped");
+    assert_eq!(answers[1], "ok");
+    assert!(answers[2].starts_with(r#"{"state":"paused","remaining_secs":1200,"#));
+    assert_eq!(answers[3], "ok");
+    assert_eq!(app.timer.state, State::Running);
+}
+
+#[test]
+fn stop_quits_after_answering() {
+    let cfg = cfg();
+    let mut app = App::new(&cfg);
+    app.timer.take_transitions();
+    let (reply, answers) = mpsc::channel();
+    let script = Script::new(FakeClock::default())
+        .wait(mins(3))
+        .command(Command::Stop, reply)
+        .wait(mins(25));
+    assert!(run(&mut app, &cfg, script));
+    assert_eq!(answers.recv().unwrap(), "ok");
+    assert_eq!(transitions(&mut app), vec![Transition::Abandon]);
+    assert_eq!(todays_no_pomodoros(&cfg.conn).unwrap(), 0);
+}
+
+#[test]
+fn reactions_come_before_the_answer() {
+    let cfg = cfg();
+    let mut app = App::new(&cfg);
+    let (reply, answers) = mpsc::channel();
+    let mut script = Script::new(FakeClock::default())
+        .wait(Duration::from_secs(2))
+        .command(Command::Abandon, reply)
+        .key(Key::Char(QUIT_KEY));
+    let mut reacted = Vec::new();
+    let quit = run_timer(&mut app, &cfg, &mut script, |app| {
+        reacted.push((transitions(app), answers.try_recv().ok()));
+        Ok(())
+    });
+    assert!(quit.unwrap());
+    assert_eq!(
+        reacted,
+        vec![
+            (vec![Transition::PomodoroStart], None),
+            (vec![], None),
+            (vec![], None),
+            // answered only once the transitions are handled
+            (vec![Transition::Abandon, Transition::PomodoroStart], None),
+            (vec![Transition::Abandon], Some(String::from("ok"))),
+        ]
+    );
+}
+
+#[test]
+fn a_script_ticks_every_second_of_its_clock() {
+    let mut script = Script::new(FakeClock::default())
+        .wait(Duration::from_millis(2500))
+        .keys("ab");
+    let mut events = Vec::new();
+    while let Ok(event) = script.next() {
+        events.push(match event {
+            Event::Tick(duration) => format!("tick {}ms", duration.as_millis()),
+            Event::Input(Key::Char(c)) => format!("key {}", c),
+            _ => String::from("other"),
+        });
+    }
+    assert_eq!(
+        events,
+        vec!["tick 1000ms", "tick 1000ms", "tick 500ms", "key a", "key b"]
+    );
+}
+
+// today's pomodoros with these tickets, the first one selected on the
+// statistics tab
+fn history(cfg: &Cfg, tickets: &[&str]) -> (App, Script<FakeClock>) {
+    for ticket in tickets {
+        let mut stat = Statistic::new(25);
+        stat.ticket_id = Some(String::from(*ticket));
+        stat.insert(&cfg.conn).unwrap();
+    }
+    let app = App::new(cfg);
+    (app, Script::new(FakeClock::default()).key(Key::BackTab))
+}
+
+fn tickets(app: &App) -> Vec<&str> {
+    app.pomodoros
+        .iter()
+        .map(|pom| pom.ticket_id.as_deref().unwrap_or_default())
+        .collect()
+}
+
+#[test]
+fn undo_restores_a_deleted_pomodoro() {
+    let cfg = cfg();
+    let (mut app, script) = history(&cfg, &["PROJ-1", "PROJ-2"]);
+    let ids: Vec<i32> = app.pomodoros.iter().map(|pom| pom.id).collect();
+    let script = script.key(Key::Down).keys("d");
+    run(&mut app, &cfg, script);
+    assert_eq!(tickets(&app), vec!["PROJ-1"]);
+    assert_eq!(todays_no_pomodoros(&cfg.conn).unwrap(), 1);
+
+    run(&mut app, &cfg, Script::new(FakeClock::default()).keys("u"));
+    assert_eq!(tickets(&app), vec!["PROJ-1", "PROJ-2"]);
+    assert_eq!(
+        app.pomodoros.iter().map(|pom| pom.id).collect::<Vec<_>>(),
+        ids
+    );
+    assert_eq!(todays_no_pomodoros(&cfg.conn).unwrap(), 2);
+    // nothing left to undo
+    run(&mut app, &cfg, Script::new(FakeClock::default()).keys("u"));
+    assert_eq!(tickets(&app), vec!["PROJ-1", "PROJ-2"]);
+}
+
+#[test]
+fn undo_brings_back_the_previous_ticket_and_note() {
+    let cfg = cfg();
+    let (mut app, script) = history(&cfg, &["PROJ-1"]);
+    let script = script
+        .keys("t")
+        .key(Key::Backspace)
+        .keys("7\n")
+        .keys("nparser\n");
+    run(&mut app, &cfg, script);
+    assert_eq!(tickets(&app), vec!["PROJ-7"]);
+    assert_eq!(app.pomodoros[0].note.as_deref(), Some("parser"));
+
+    // newest first
+    run(&mut app, &cfg, Script::new(FakeClock::default()).keys("u"));
+    assert_eq!(tickets(&app), vec!["PROJ-7"]);
+    assert_eq!(app.pomodoros[0].note, None);
+    run(&mut app, &cfg, Script::new(FakeClock::default()).keys("u"));
+    assert_eq!(tickets(&app), vec!["PROJ-1"]);
+
+    // an emptied ticket comes back too
+    let script = (0.."PROJ-1".len())
+        .fold(Script::new(FakeClock::default()).keys("t"), |script, _| {
+            script.key(Key::Backspace)
+        });
+    run(&mut app, &cfg, script.keys("\n"));
+    assert_eq!(app.pomodoros[0].ticket_id, None);
+    run(&mut app, &cfg, Script::new(FakeClock::default()).keys("u"));
+    assert_eq!(tickets(&app), vec!["PROJ-1"]);
+}
+
+#[test]
+fn purge_removes_only_deleted_pomodoros() {
+    let cfg = cfg();
+    let (mut app, script) = history(&cfg, &["PROJ-1", "PROJ-2", "PROJ-3"]);
+    run(&mut app, &cfg, script.key(Key::Down).keys("d"));
+    assert_eq!(tickets(&app), vec!["PROJ-1", "PROJ-3"]);
+
+    assert_eq!(Statistic::purge(&cfg.conn).unwrap(), 1);
+    assert_eq!(Statistic::purge(&cfg.conn).unwrap(), 0);
+    // gone for good, undo has nothing to restore
+    run(&mut app, &cfg, Script::new(FakeClock::default()).keys("u"));
+    assert_eq!(tickets(&app), vec!["PROJ-1", "PROJ-3"]);
+    assert_eq!(todays_no_pomodoros(&cfg.conn).unwrap(), 2);
+}
@@ -0,0 +1,81 @@
+//! Lines understood on the control socket.
+
+use pomodorust::control::{key_name, Command};
+use termion::event::Key;
+
+fn command(line: &str) -> Result<Command, String> {
+    line.parse().map_err(|e: failure::Error| e.to_string())
+}
+
+#[test]
+fn keys_sent_by_attached_clients_come_back_the_same() {
+    let keys = [
+        Key::Char('a'),
+        Key::Char(' '),
+        Key::Char('\n'),
+        Key::Char(';'),
+        Key::Char('ü'),
+        Key::Char('🍅'),
+        Key::Up,
+        Key::Down,
+        Key::BackTab,
+        Key::Backspace,
+        Key::Esc,
+    ];
+    for &key in &keys {
+        let name = key_name(key).unwrap();
+        assert_eq!(command(&format!("key {}", name)), Ok(Command::Key(key)));
+    }
+    assert_eq!(key_name(Key::F(1)), None);
+    assert_eq!(key_name(Key::Ctrl('c')), None);
+}
+
+#[test]
+fn unknown_keys_are_refused() {
+    assert_eq!(command("key"), Err(String::from("unknown key ''")));
+    assert_eq!(command("key left"), Err(String::from("unknown key 'left'")));
+    assert_eq!(command("key up 1"), Err(String::from("unknown key 'up 1'")));
+    assert_eq!(
+        command("key char 55296"),
+        Err(String::from("'55296' is not a character code"))
+    );
+}
+
+#[test]
+fn set_ticket_takes_an_optional_argument() {
+    assert_eq!(
+        command("set-ticket PROJ-1\n"),
+        Ok(Command::SetTicket(Some(String::from("PROJ-1"))))
+    );
+    assert_eq!(
+        command("set-ticket  code review "),
+        Ok(Command::SetTicket(Some(String::from("code review"))))
+    );
+    // clears the ticket
+    assert_eq!(command("set-ticket"), Ok(Command::SetTicket(None)));
+    assert_eq!(command("set-ticket   "), Ok(Command::SetTicket(None)));
+}
+
+#[test]
+fn commands_are_parsed_and_unknown_ones_refused() {
+    for (line, expected) in &[
+        ("pause", Command::Pause),
+        ("resume\n", Command::Resume),
+        ("skip", Command::Skip),
+        ("abandon", Command::Abandon),
+        ("status", Command::Status),
+        ("snapshot", Command::Snapshot),
+        ("stop", Command::Stop),
+    ] {
+        assert_eq!(command(line).ok().as_ref(), Some(expected));
+    }
+    assert_eq!(
+        command("lunch"),
+        Err(String::from("unknown command 'lunch'"))
+    );
+    assert_eq!(
+        command("Pause"),
+        Err(String::from("unknown command 'Pause'"))
+    );
+    assert_eq!(command(""), Err(String::from("unknown command ''")));
+}
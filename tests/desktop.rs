@@ -0,0 +1,112 @@
+//! Desktop notifications against a private `dbus-daemon`, which stands in for
+//! the session bus with a fake notification service.
+
+use dbus::arg::PropMap;
+use dbus::blocking::Connection;
+use dbus::channel::{MatchingReceiver, Sender};
+use dbus::message::{MatchRule, Message};
+use pomodorust::config::Cfg;
+use pomodorust::control::Command;
+use pomodorust::desktop::Desktop;
+use pomodorust::events::Event;
+use pomodorust::state::App;
+use pomodorust::timer::{Durations, State, Transition};
+use std::io::{BufRead, BufReader};
+use std::process::{Child, Command as Process, Stdio};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+struct Bus(Child);
+
+impl Drop for Bus {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+// the feature that builds these tests needs dbus, whose dbus-daemon they run
+fn private_bus() -> (Bus, String) {
+    let mut child = Process::new("dbus-daemon")
+        .args(["--session", "--nofork", "--print-address"])
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("dbus-daemon on the PATH");
+    let mut address = String::new();
+    BufReader::new(child.stdout.take().unwrap())
+        .read_line(&mut address)
+        .unwrap();
+    (Bus(child), address.trim().to_string())
+}
+
+// app name, replaced ID, icon, summary, body, actions, hints and timeout
+type Notify = (
+    String,
+    u32,
+    String,
+    String,
+    String,
+    Vec<String>,
+    PropMap,
+    i32,
+);
+
+// answers every Notify with the ID 42 and clicks the first action
+fn fake_service(address: String, notified: mpsc::Sender<(String, Vec<String>)>) {
+    thread::spawn(move || {
+        let mut channel = dbus::channel::Channel::open_private(&address).unwrap();
+        channel.register().unwrap();
+        let conn = Connection::from(channel);
+        conn.request_name("org.freedesktop.Notifications", false, true, false)
+            .unwrap();
+        conn.start_receive(
+            MatchRule::new_method_call(),
+            Box::new(move |msg: Message, conn: &Connection| {
+                let (_, _, _, summary, _, actions, _, _): Notify = msg.read_all().unwrap();
+                let _ = conn.send(msg.method_return().append1(42u32));
+                let signal = Message::new_signal(
+                    "/org/freedesktop/Notifications",
+                    "org.freedesktop.Notifications",
+                    "ActionInvoked",
+                )
+                .unwrap()
+                .append2(42u32, actions[0].clone());
+                let _ = conn.send(signal);
+                let _ = notified.send((summary, actions));
+                true
+            }),
+        );
+        // until the bus goes away with the test
+        while conn.process(Duration::from_millis(100)).is_ok() {}
+    });
+}
+
+fn cfg() -> Cfg {
+    Cfg::in_memory(Durations::default()).unwrap()
+}
+
+#[test]
+fn actions_become_commands() {
+    let (_bus, address) = private_bus();
+    let (notified_tx, notified) = mpsc::channel();
+    fake_service(address.clone(), notified_tx);
+    thread::sleep(Duration::from_millis(200));
+
+    let (tx, events) = mpsc::channel();
+    let desktop = Desktop::connect_to(&address, tx).expect("connects to the private bus");
+    let cfg = cfg();
+    let mut app = App::new(&cfg);
+    app.timer.state = State::NextBreak(cfg.short_break);
+    desktop.notify(Transition::PomodoroFinish, &app.timer);
+
+    let (summary, actions) = notified.recv_timeout(TIMEOUT).unwrap();
+    assert_eq!(summary, "Pomodoro finished");
+    assert_eq!(actions, vec!["skip", "Skip break", "pause", "Pause"]);
+    match events.recv_timeout(TIMEOUT).unwrap() {
+        Event::Command(command, _) => assert_eq!(command, Command::Skip),
+        _ => panic!("expected a command"),
+    }
+}
@@ -0,0 +1,214 @@
+//! Exporting recorded pomodoros.
+
+use chrono::{Duration, NaiveDate, NaiveDateTime};
+use pomodorust::database::{Pomodoro, Worklog};
+use pomodorust::export::{ics, jira_worklog, org, timew, Breaks, Rounding, RoundingMode};
+
+fn time(text: &str) -> NaiveDateTime {
+    NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M").unwrap()
+}
+
+fn pomodoro(id: i32, created_at: &str, ticket: Option<&str>, note: Option<&str>) -> Pomodoro {
+    Pomodoro {
+        id,
+        created_at: time(created_at),
+        duration: 25,
+        ticket_id: ticket.map(String::from),
+        note: note.map(String::from),
+        manual: false,
+        deleted_at: None,
+    }
+}
+
+fn written<F: FnOnce(&mut Vec<u8>)>(write: F) -> String {
+    let mut out = Vec::new();
+    write(&mut out);
+    String::from_utf8(out).unwrap()
+}
+
+// the lines of a calendar without the time it was written at
+fn calendar(pomodoros: &[Pomodoro], breaks: Option<&Breaks>) -> Vec<String> {
+    written(|out| ics(out, pomodoros, breaks).unwrap())
+        .split("\r\n")
+        .filter(|line| !line.starts_with("DTSTAMP:"))
+        .map(String::from)
+        .collect()
+}
+
+#[test]
+fn an_empty_calendar() {
+    assert_eq!(
+        calendar(&[], None),
+        vec![
+            "BEGIN:VCALENDAR",
+            "VERSION:2.0",
+            "PRODID:-//pomodorust//pomodorust//EN",
+            "END:VCALENDAR",
+            ""
+        ]
+    );
+}
+
+#[test]
+fn pomodoros_are_events_with_escaped_text() {
+    let lines = calendar(
+        &[pomodoro(
+            7,
+            "2020-01-02 09:25",
+            Some("PROJ-1"),
+            Some("a, b; c\\d\nnext"),
+        )],
+        None,
+    );
+    assert_eq!(
+        lines[3..9],
+        [
+            "BEGIN:VEVENT",
+            "UID:pomodoro-7@pomodorust",
+            "DTSTART:20200102T090000Z",
+            "DTEND:20200102T092500Z",
+            "SUMMARY:PROJ-1",
+            "DESCRIPTION:a\\, b\\; c\\\\d\\nnext",
+        ]
+    );
+}
+
+#[test]
+fn long_lines_are_folded() {
+    let note = "x".repeat(100);
+    let lines = calendar(&[pomodoro(1, "2020-01-02 09:25", None, Some(&note))], None);
+    let description = lines
+        .iter()
+        .position(|line| line.starts_with("DESCRIPTION:"))
+        .unwrap();
+    assert_eq!(lines[description].len(), 75);
+    assert_eq!(
+        lines[description + 1],
+        format!(" {}", "x".repeat(100 + 12 - 75))
+    );
+    assert_eq!(lines[description - 1], "SUMMARY:Pomodoro");
+}
+
+#[test]
+fn breaks_follow_the_pomodoros_of_a_day() {
+    let breaks = Breaks {
+        short: Duration::minutes(5),
+        long: Duration::minutes(15),
+    };
+    let pomodoros = [
+        pomodoro(1, "2020-01-02 09:25", None, None),
+        // started before the break would have ended
+        pomodoro(2, "2020-01-02 09:52", None, None),
+        pomodoro(3, "2020-01-02 10:30", None, None),
+        pomodoro(4, "2020-01-02 11:00", None, None),
+        pomodoro(5, "2020-01-02 11:40", None, None),
+        pomodoro(6, "2020-01-03 09:25", None, None),
+    ];
+    let starts: Vec<String> = calendar(&pomodoros, Some(&breaks))
+        .windows(2)
+        .filter(|pair| pair[0].starts_with("UID:break-"))
+        .map(|pair| pair[1].clone())
+        .collect();
+    let ends: Vec<String> = calendar(&pomodoros, Some(&breaks))
+        .into_iter()
+        .filter(|line| line.starts_with("DTEND:"))
+        .skip(pomodoros.len())
+        .collect();
+    assert_eq!(
+        starts
+            .iter()
+            .zip(&ends)
+            .map(|(start, end)| format!("{} {}", start, end))
+            .collect::<Vec<_>>(),
+        vec![
+            "DTSTART:20200102T092500Z DTEND:20200102T092700Z",
+            "DTSTART:20200102T095200Z DTEND:20200102T095700Z",
+            "DTSTART:20200102T103000Z DTEND:20200102T103500Z",
+            // the fourth of the day is followed by a long one
+            "DTSTART:20200102T110000Z DTEND:20200102T111500Z",
+            "DTSTART:20200102T114000Z DTEND:20200102T114500Z",
+            // and the count starts again the next day
+            "DTSTART:20200103T092500Z DTEND:20200103T093000Z",
+        ]
+    );
+}
+
+#[test]
+fn logged_time_is_rounded_but_never_away() {
+    let rounding = |step_mins, mode| Rounding { step_mins, mode };
+    assert_eq!(rounding(15, RoundingMode::Up).round(25), 30);
+    assert_eq!(rounding(15, RoundingMode::Up).round(30), 30);
+    assert_eq!(rounding(15, RoundingMode::Down).round(25), 15);
+    assert_eq!(rounding(15, RoundingMode::Down).round(10), 15);
+    assert_eq!(rounding(15, RoundingMode::Nearest).round(22), 15);
+    assert_eq!(rounding(15, RoundingMode::Nearest).round(23), 30);
+    assert_eq!(rounding(15, RoundingMode::Nearest).round(0), 0);
+    assert_eq!(rounding(1, RoundingMode::Up).round(25), 25);
+    assert!("sideways".parse::<RoundingMode>().is_err());
+}
+
+#[test]
+fn worklogs_are_written_for_the_jira_importer() {
+    let worklogs = [Worklog {
+        ticket_id: String::from("PROJ-1"),
+        day: NaiveDate::from_ymd(2020, 1, 2),
+        started_at: time("2020-01-02 09:00"),
+        duration: 50,
+        notes: Some(String::from("parser; tests")),
+    }];
+    let rounding = Rounding {
+        step_mins: 15,
+        mode: RoundingMode::Up,
+    };
+    let csv = written(|out| jira_worklog(out, &worklogs, &rounding, "ada").unwrap());
+    assert_eq!(
+        csv,
+        "Issue Key,Log Work\nPROJ-1,\"parser, tests;2020-01-02 09:00;ada;3600\"\n"
+    );
+    let csv = written(|out| jira_worklog(out, &[], &rounding, "ada").unwrap());
+    assert_eq!(csv, "Issue Key,Log Work\n");
+}
+
+#[test]
+fn timewarrior_lines_quote_tags_and_annotations() {
+    let pomodoros = [
+        pomodoro(1, "2020-01-02 09:25", Some("PROJ-1"), None),
+        pomodoro(
+            2,
+            "2020-01-02 09:55",
+            Some("code review"),
+            Some("said \"ok\""),
+        ),
+    ];
+    assert_eq!(
+        written(|out| timew(out, &pomodoros).unwrap()),
+        "inc 20200102T090000Z - 20200102T092500Z # pomodoro PROJ-1
+inc 20200102T093000Z - 20200102T095500Z # pomodoro \"code review\" # \"said \\\"ok\\\"\"
+"
+    );
+    assert_eq!(written(|out| timew(out, &[]).unwrap()), "");
+}
+
+#[test]
+fn org_headings_group_the_clocks_of_a_ticket() {
+    let pomodoros = [
+        pomodoro(1, "2020-01-02 09:25", Some("PROJ-1"), Some("parser")),
+        pomodoro(2, "2020-01-02 09:55", None, None),
+        pomodoro(3, "2020-01-02 10:25", Some("PROJ-1"), None),
+    ];
+    let text = written(|out| org(out, &pomodoros).unwrap());
+    let lines: Vec<&str> = text.lines().collect();
+    // clocks are in local time, so only their shape is checked
+    let clock =
+        |line: &str| line.starts_with("  CLOCK: [2020-01-0") && line.ends_with("] =>  0:25");
+    assert_eq!(lines.len(), 10);
+    assert_eq!(lines[0..2], ["* PROJ-1", "  :LOGBOOK:"]);
+    assert!(clock(lines[2]) && clock(lines[3]));
+    assert_eq!(
+        lines[4..8],
+        ["  :END:", "  parser", "* Pomodoros", "  :LOGBOOK:"]
+    );
+    assert!(clock(lines[8]));
+    assert_eq!(lines[9], "  :END:");
+    assert_eq!(written(|out| org(out, &[]).unwrap()), "");
+}
@@ -0,0 +1,106 @@
+//! Hooks run as shell commands on the transitions of the timer.
+
+use pomodorust::hooks::Hooks;
+use pomodorust::timer::{Durations, Timer, Transition};
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+fn temp_file(name: &str) -> PathBuf {
+    let path = env::temp_dir().join(format!("pomodorust-hook-{}-{}", name, process::id()));
+    let _ = fs::remove_file(&path);
+    path
+}
+
+fn hooks(transition: &str, command: &str, timeout: Duration) -> Hooks {
+    let mut commands = HashMap::new();
+    commands.insert(String::from(transition), String::from(command));
+    Hooks::new(&commands, timeout).unwrap()
+}
+
+// runs the hook of a finished pomodoro, returns its failure if any
+fn finish(hooks: &Hooks, timer: &Timer) -> Option<String> {
+    let (tx, failures) = mpsc::channel();
+    hooks.run(
+        timer,
+        Transition::PomodoroFinish,
+        Duration::from_secs(25 * 60),
+        move |error| tx.send(error).unwrap(),
+    );
+    // the sender is dropped without a failure
+    failures.recv_timeout(Duration::from_secs(10)).ok()
+}
+
+#[test]
+fn the_command_gets_the_timer_in_its_environment() {
+    let out = temp_file("env");
+    let command = format!(
+        "echo \"$POMODORUST_TRANSITION $POMODORUST_STATE $POMODORUST_TICKET \
+         $POMODORUST_DURATION $POMODORUST_COUNT\" > {}",
+        out.display()
+    );
+    let hooks = hooks("pomodoro_finish", &command, Duration::from_secs(10));
+    let mut timer = Timer::new(Durations::default(), 3);
+    timer.ticket = Some(String::from("PROJ-1"));
+    assert_eq!(finish(&hooks, &timer), None);
+    assert_eq!(
+        fs::read_to_string(&out).unwrap(),
+        "pomodoro_finish running PROJ-1 25 3\n"
+    );
+    fs::remove_file(out).unwrap();
+}
+
+#[test]
+fn a_failing_command_is_reported() {
+    let hooks = hooks("pomodoro_finish", "exit 3", Duration::from_secs(10));
+    let timer = Timer::new(Durations::default(), 0);
+    assert_eq!(
+        finish(&hooks, &timer).as_deref(),
+        Some("pomodoro_finish: exited with 3")
+    );
+}
+
+#[test]
+fn a_command_running_too_long_is_killed() {
+    let pid_file = temp_file("pid");
+    let command = format!("echo $$ > {}; exec sleep 30", pid_file.display());
+    let hooks = hooks("pomodoro_finish", &command, Duration::from_secs(1));
+    let timer = Timer::new(Durations::default(), 0);
+    let started = Instant::now();
+    assert_eq!(
+        finish(&hooks, &timer).as_deref(),
+        Some("pomodoro_finish: timed out after 1s")
+    );
+    assert!(started.elapsed() < Duration::from_secs(5));
+    let pid: i32 = fs::read_to_string(&pid_file)
+        .unwrap()
+        .trim()
+        .parse()
+        .unwrap();
+    // gone and reaped
+    assert_ne!(unsafe { libc::kill(pid, 0) }, 0);
+    fs::remove_file(pid_file).unwrap();
+}
+
+#[test]
+fn transitions_without_a_hook_run_nothing() {
+    let hooks = hooks("break_end", "exit 1", Duration::from_secs(10));
+    let timer = Timer::new(Durations::default(), 0);
+    assert_eq!(finish(&hooks, &timer), None);
+}
+
+#[test]
+fn unknown_hooks_are_refused() {
+    let mut commands = HashMap::new();
+    commands.insert(String::from("lunch"), String::from("true"));
+    let error = Hooks::new(&commands, Duration::from_secs(10))
+        .err()
+        .unwrap();
+    assert!(error
+        .to_string()
+        .starts_with("Unknown hook 'lunch', hooks are "));
+}
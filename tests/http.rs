@@ -0,0 +1,162 @@
+//! Requests to the JSON API, with a channel standing in for the timer.
+
+use diesel::prelude::Connection;
+use diesel::sqlite::SqliteConnection;
+use pomodorust::control::Command;
+use pomodorust::database::create_table;
+use pomodorust::events::Event;
+use pomodorust::http::{serve, Address};
+use serde_json::{json, Value};
+use std::env;
+use std::fs;
+use std::net::SocketAddr;
+use std::process;
+use std::sync::mpsc;
+use std::thread;
+
+const LOOPBACK: Address = Address {
+    address: "127.0.0.1:0",
+    public: false,
+};
+
+// serves on a free port, the timer answers every command with `answer` and
+// hands the commands over
+fn timer(name: &str, answer: &'static str) -> (SocketAddr, mpsc::Receiver<Command>) {
+    let db = env::temp_dir().join(format!("pomodorust-http-{}-{}.db", name, process::id()));
+    let _ = fs::remove_file(&db);
+    create_table(&SqliteConnection::establish(&db.to_string_lossy()).unwrap()).unwrap();
+    let (tx, rx) = mpsc::channel();
+    let (commands_tx, commands) = mpsc::channel();
+    thread::spawn(move || {
+        for event in rx {
+            if let Event::Command(command, reply) = event {
+                reply.send(String::from(answer)).unwrap();
+                commands_tx.send(command).unwrap();
+            }
+        }
+    });
+    (serve(LOOPBACK, db, tx).unwrap(), commands)
+}
+
+// the status code and the JSON of the answer, errors included
+fn answer(result: Result<ureq::Response, ureq::Error>) -> (u16, Value) {
+    let response = match result {
+        Ok(response) => response,
+        Err(ureq::Error::Status(_, response)) => response,
+        Err(e) => panic!("{}", e),
+    };
+    let status = response.status();
+    (
+        status,
+        serde_json::from_str(&response.into_string().unwrap()).unwrap(),
+    )
+}
+
+fn get(addr: SocketAddr, path: &str) -> (u16, Value) {
+    answer(ureq::get(&format!("http://{}{}", addr, path)).call())
+}
+
+fn post(addr: SocketAddr, path: &str) -> (u16, Value) {
+    answer(ureq::post(&format!("http://{}{}", addr, path)).call())
+}
+
+#[test]
+fn posts_control_the_timer() {
+    let (addr, commands) = timer("control", "ok");
+    for (path, command) in &[
+        ("/pause", Command::Pause),
+        ("/resume", Command::Resume),
+        ("/skip", Command::Skip),
+        ("/abandon", Command::Abandon),
+    ] {
+        assert_eq!(post(addr, path), (200, json!({ "result": "ok" })));
+        assert_eq!(&commands.recv().unwrap(), command);
+    }
+}
+
+#[test]
+fn a_refused_command_is_a_conflict() {
+    let (addr, _commands) = timer("refused", "error: only breaks can be skipped");
+    assert_eq!(
+        post(addr, "/skip"),
+        (409, json!({ "error": "only breaks can be skipped" }))
+    );
+}
+
+#[test]
+fn the_status_is_the_json_of_the_timer() {
+    let (addr, commands) = timer("status", r#"{"phase":"pomodoro","remaining":1500}"#);
+    assert_eq!(
+        get(addr, "/status"),
+        (200, json!({ "phase": "pomodoro", "remaining": 1500 }))
+    );
+    assert_eq!(commands.recv().unwrap(), Command::Status);
+}
+
+#[test]
+fn a_stopped_timer_is_unavailable() {
+    let db = env::temp_dir().join(format!("pomodorust-http-stopped-{}.db", process::id()));
+    let _ = fs::remove_file(&db);
+    create_table(&SqliteConnection::establish(&db.to_string_lossy()).unwrap()).unwrap();
+    let (tx, rx) = mpsc::channel();
+    drop(rx);
+    let addr = serve(LOOPBACK, db, tx).unwrap();
+    assert_eq!(
+        post(addr, "/pause"),
+        (503, json!({ "error": "timer stopped" }))
+    );
+}
+
+#[test]
+fn wrong_methods_and_paths_are_refused() {
+    let (addr, commands) = timer("refused-requests", "ok");
+    assert_eq!(get(addr, "/pause").0, 405);
+    assert_eq!(post(addr, "/stats").0, 405);
+    assert_eq!(
+        get(addr, "/stop"),
+        (404, json!({ "error": "no such endpoint '/stop'" }))
+    );
+    assert!(commands.try_recv().is_err());
+}
+
+#[test]
+fn history_is_read_from_the_database() {
+    let (addr, _commands) = timer("history", "ok");
+    assert_eq!(get(addr, "/pomodoros"), (200, json!([])));
+    assert_eq!(
+        get(addr, "/stats?since=2020-01-01&group_by=week"),
+        (200, json!([]))
+    );
+    assert_eq!(
+        get(addr, "/stats?since=yesterday"),
+        (
+            400,
+            json!({ "error": "'yesterday' is not a date like 2020-01-31" })
+        )
+    );
+}
+
+#[test]
+fn only_loopback_is_served_unless_public() {
+    let (tx, _rx) = mpsc::channel();
+    let db = env::temp_dir().join("pomodorust-http-unused.db");
+    let everywhere = Address {
+        address: "0.0.0.0:0",
+        public: false,
+    };
+    let error = serve(everywhere, db.clone(), tx.clone()).err().unwrap();
+    assert_eq!(
+        error.to_string(),
+        "0.0.0.0:0 is not a loopback address, `--http-public` serves the API on it anyway."
+    );
+    let public = Address {
+        public: true,
+        ..everywhere
+    };
+    assert!(serve(public, db.clone(), tx.clone()).is_ok());
+    let localhost = Address {
+        address: "localhost:0",
+        public: false,
+    };
+    assert!(serve(localhost, db, tx).is_ok());
+}
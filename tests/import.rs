@@ -0,0 +1,191 @@
+//! Importing pomodoros from csv and timewarrior into an in-memory database.
+
+use chrono::{Local, TimeZone};
+use diesel::prelude::Connection;
+use diesel::sqlite::SqliteConnection;
+use pomodorust::database::{create_table, Pomodoro, Statistic};
+use pomodorust::import::{
+    import_csv, import_timew, parse_local_timestamp, parse_timestamp, ColumnMapping, ImportReport,
+};
+
+fn conn() -> SqliteConnection {
+    let conn = SqliteConnection::establish(":memory:").unwrap();
+    create_table(&conn).unwrap();
+    conn
+}
+
+fn all(conn: &SqliteConnection) -> Vec<Pomodoro> {
+    let day = "2020-01-02".parse().unwrap();
+    Pomodoro::pomodoros_between(conn, day, day).unwrap()
+}
+
+const CSV: &str = "created_at,duration,ticket_id,note
+2020-01-02 09:25,25,PROJ-1,
+2020-01-02 09:55,25,,review
+";
+
+#[test]
+fn importing_again_does_not_restore_deleted_pomodoros() {
+    let conn = conn();
+    import_csv(&conn, CSV.as_bytes(), &ColumnMapping::default(), false).unwrap();
+    let first = all(&conn)[0].id;
+    Statistic::delete(&conn, first).unwrap();
+
+    let report = import_csv(&conn, CSV.as_bytes(), &ColumnMapping::default(), false).unwrap();
+    assert!(report.inserted.is_empty());
+    assert_eq!(report.duplicates.len(), 2);
+    assert_eq!(all(&conn).len(), 1);
+}
+
+fn import(conn: &SqliteConnection, csv: &str) -> Result<ImportReport, failure::Error> {
+    import_csv(conn, csv.as_bytes(), &ColumnMapping::default(), false)
+}
+
+#[test]
+fn a_bad_timestamp_names_its_row_and_imports_nothing() {
+    let conn = conn();
+    let error = import(
+        &conn,
+        "created_at,duration\n2020-01-02 09:25,25\nyesterday,25\n",
+    )
+    .err()
+    .unwrap();
+    assert_eq!(
+        error.to_string(),
+        "Row 2: Cannot parse timestamp 'yesterday'."
+    );
+    assert!(all(&conn).is_empty());
+}
+
+#[test]
+fn a_bad_duration_names_its_row() {
+    let conn = conn();
+    let error = import(&conn, "created_at,duration\n2020-01-02 09:25,\n")
+        .err()
+        .unwrap();
+    assert_eq!(
+        error.to_string(),
+        "Row 1: invalid duration (cannot parse integer from empty string)."
+    );
+}
+
+#[test]
+fn rows_repeated_in_one_file_are_imported_once() {
+    let conn = conn();
+    let report = import(
+        &conn,
+        "created_at,duration\n2020-01-02 09:25,25\n2020-01-02T09:25:00,25\n2020-01-02 09:25,15\n",
+    )
+    .unwrap();
+    assert_eq!(report.inserted.len(), 2);
+    assert_eq!(report.duplicates.len(), 1);
+    assert_eq!(all(&conn).len(), 2);
+}
+
+#[test]
+fn a_dry_run_reports_without_inserting() {
+    let conn = conn();
+    let report = import_csv(&conn, CSV.as_bytes(), &ColumnMapping::default(), true).unwrap();
+    assert_eq!(report.inserted.len(), 2);
+    assert!(all(&conn).is_empty());
+}
+
+#[test]
+fn empty_input_imports_nothing() {
+    let conn = conn();
+    let report = import(&conn, "created_at,duration,ticket_id,note\n").unwrap();
+    assert!(report.inserted.is_empty() && report.duplicates.is_empty());
+    // without a header there are no columns to find
+    let error = import(&conn, "").err().unwrap();
+    assert_eq!(error.to_string(), "Missing column 'created_at'.");
+}
+
+#[test]
+fn columns_are_mapped_by_header() {
+    let conn = conn();
+    let mapping =
+        ColumnMapping::parse("created_at=Start, duration=Minutes,ticket_id=Issue").unwrap();
+    let csv = "Issue,Minutes,Start\n PROJ-2 ,25,2020-01-02 10:00\n";
+    import_csv(&conn, csv.as_bytes(), &mapping, false).unwrap();
+    let pom = &all(&conn)[0];
+    assert_eq!(pom.ticket_id.as_deref(), Some("PROJ-2"));
+    assert_eq!(pom.note, None);
+    assert!(pom.manual);
+
+    assert!(ColumnMapping::parse("created_at").is_err());
+    assert!(ColumnMapping::parse("started=Start").is_err());
+}
+
+#[test]
+fn timestamps_are_read_as_utc() {
+    let utc = |text| parse_timestamp(text).unwrap().to_string();
+    assert_eq!(utc("2020-01-02 09:25"), "2020-01-02 09:25:00");
+    assert_eq!(utc(" 2020-01-02T09:25:30 "), "2020-01-02 09:25:30");
+    assert_eq!(utc("2020-01-02T10:25:30.250+01:00"), "2020-01-02 09:25:30");
+    // an offset can move it to another day
+    assert_eq!(utc("2020-01-01T23:30:00-01:00"), "2020-01-02 00:30:00");
+    for bad in &["", "2020-01-02", "02.01.2020 09:25", "2020-02-30 09:25"] {
+        assert!(parse_timestamp(bad).is_err(), "{}", bad);
+    }
+}
+
+#[test]
+fn typed_times_are_local() {
+    let typed = parse_local_timestamp("2020-01-02 14:30").unwrap();
+    assert_eq!(
+        Local.from_utc_datetime(&typed).naive_local().to_string(),
+        "2020-01-02 14:30:00"
+    );
+    // an offset is still an offset
+    assert_eq!(
+        parse_local_timestamp("2020-01-02T14:30:00+01:00")
+            .unwrap()
+            .to_string(),
+        "2020-01-02 13:30:00"
+    );
+    assert!(parse_local_timestamp("14:30").is_err());
+}
+
+#[test]
+fn timewarrior_intervals_become_pomodoros() {
+    let conn = conn();
+    let json = r#"[
+        {"start": "20200102T090000Z", "end": "20200102T092500Z", "tags": ["pomodoro", "PROJ-1"], "annotation": "review"},
+        {"start": "20200102T093000Z", "end": "20200102T095429Z"},
+        {"start": "20200102T100000Z", "tags": ["pomodoro"]}
+    ]"#;
+    let report = import_timew(&conn, json.as_bytes(), false).unwrap();
+    assert_eq!(report.inserted.len(), 2);
+    let pomodoros = all(&conn);
+    assert_eq!(pomodoros[0].created_at.to_string(), "2020-01-02 09:25:00");
+    assert_eq!(pomodoros[0].duration, 25);
+    assert_eq!(pomodoros[0].ticket_id.as_deref(), Some("PROJ-1"));
+    assert_eq!(pomodoros[0].note.as_deref(), Some("review"));
+    // rounded to whole minutes
+    assert_eq!(pomodoros[1].duration, 24);
+    assert_eq!(pomodoros[1].ticket_id, None);
+
+    let again = import_timew(&conn, json.as_bytes(), false).unwrap();
+    assert_eq!(again.duplicates.len(), 2);
+}
+
+#[test]
+fn timewarrior_input_must_be_well_formed() {
+    let conn = conn();
+    assert!(import_timew(&conn, &b"[]"[..], false)
+        .unwrap()
+        .inserted
+        .is_empty());
+    assert!(import_timew(&conn, &b""[..], false).is_err());
+    let error = import_timew(
+        &conn,
+        &br#"[{"start": "2020-01-02 09:00", "end": "20200102T092500Z"}]"#[..],
+        false,
+    )
+    .err()
+    .unwrap();
+    assert!(error
+        .to_string()
+        .starts_with("Cannot parse timewarrior time '2020-01-02 09:00'"));
+    assert!(all(&conn).is_empty());
+}
@@ -0,0 +1,95 @@
+//! The lock that keeps two timers off one database.
+
+use pomodorust::lock::{holder, lock_path, Lock};
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process::{self, Command};
+use std::sync::{Arc, Barrier};
+use std::thread;
+
+fn db_path(name: &str) -> PathBuf {
+    let path = env::temp_dir().join(format!("pomodorust-lock-{}-{}.db", name, process::id()));
+    let _ = fs::remove_file(lock_path(&path));
+    path
+}
+
+// the PID of a process that has already exited
+fn dead_pid() -> u32 {
+    let mut child = Command::new("true").spawn().unwrap();
+    child.wait().unwrap();
+    child.id()
+}
+
+#[test]
+fn a_second_timer_is_refused() {
+    let db = db_path("second");
+    let _lock = Lock::acquire(&db, false).unwrap();
+    let error = Lock::acquire(&db, false).err().unwrap();
+    assert_eq!(
+        error.to_string(),
+        format!(
+            "Another timer (PID {}) is running on {}.",
+            process::id(),
+            db.display()
+        )
+    );
+    assert_eq!(holder(&db), Some(process::id() as i32));
+}
+
+#[test]
+fn the_lock_of_a_dead_timer_is_taken_over() {
+    let db = db_path("dead");
+    fs::write(lock_path(&db), format!("{}\n", dead_pid())).unwrap();
+    assert_eq!(holder(&db), None);
+    let lock = Lock::acquire(&db, false).unwrap();
+    assert_eq!(
+        fs::read_to_string(lock_path(&db)).unwrap(),
+        format!("{}\n", process::id())
+    );
+    drop(lock);
+    assert!(!lock_path(&db).exists());
+}
+
+#[test]
+fn an_unreadable_pid_is_not_taken_for_stale() {
+    let db = db_path("unreadable");
+    fs::write(lock_path(&db), "").unwrap();
+    assert!(Lock::acquire(&db, false).is_err());
+    assert_eq!(fs::read_to_string(lock_path(&db)).unwrap(), "");
+    fs::remove_file(lock_path(&db)).unwrap();
+}
+
+#[test]
+fn a_forced_timer_keeps_its_lock_when_the_first_one_ends() {
+    let db = db_path("forced");
+    let first = Lock::acquire(&db, false).unwrap();
+    let _second = Lock::acquire(&db, true).unwrap();
+    drop(first);
+    assert_eq!(holder(&db), Some(process::id() as i32));
+}
+
+#[test]
+fn only_one_of_the_timers_starting_together_gets_the_lock() {
+    let db = db_path("race");
+    fs::write(lock_path(&db), format!("{}\n", dead_pid())).unwrap();
+    let barrier = Arc::new(Barrier::new(8));
+    let starts: Vec<_> = (0..8)
+        .map(|_| {
+            let (db, barrier) = (db.clone(), barrier.clone());
+            thread::spawn(move || {
+                barrier.wait();
+                let lock = Lock::acquire(&db, false).ok();
+                // everyone tried before the winner lets go
+                barrier.wait();
+                lock.is_some()
+            })
+        })
+        .collect();
+    let won = starts
+        .into_iter()
+        .map(|start| start.join().unwrap())
+        .filter(|&won| won)
+        .count();
+    assert_eq!(won, 1);
+}
@@ -0,0 +1,147 @@
+//! The Prometheus exposition of the timer.
+
+use diesel::prelude::Connection;
+use diesel::sqlite::SqliteConnection;
+use pomodorust::database::{create_table, Statistic};
+use pomodorust::metrics::{write_textfile, Sample};
+use pomodorust::status::Status;
+use std::env;
+use std::fs;
+use std::process;
+
+fn status(state: &str, remaining_secs: u64) -> Status {
+    Status {
+        state: String::from(state),
+        remaining_secs,
+        count: 2,
+        ticket: None,
+        updated_at: 0,
+    }
+}
+
+fn sample() -> Sample {
+    Sample {
+        status: status("running", 754),
+        completed: 3,
+        focused_secs: 4500,
+        tickets: vec![(String::new(), 1), (String::from("PROJ-1"), 2)],
+    }
+}
+
+fn pomodoro(conn: &SqliteConnection, ticket: Option<&str>) -> i32 {
+    Statistic {
+        created_at: Some("2020-01-02T09:25:00".parse().unwrap()),
+        duration: 25,
+        ticket_id: ticket.map(String::from),
+        note: None,
+        manual: false,
+    }
+    .insert_row(conn)
+    .unwrap()
+    .id
+}
+
+#[test]
+fn a_sample_is_rendered_in_the_text_format() {
+    assert_eq!(
+        sample().render(),
+        r#"# HELP pomodorust_pomodoros_completed Pomodoros recorded and not deleted.
+# TYPE pomodorust_pomodoros_completed gauge
+pomodorust_pomodoros_completed 3
+# HELP pomodorust_focused_seconds Time spent in recorded pomodoros.
+# TYPE pomodorust_focused_seconds gauge
+pomodorust_focused_seconds 4500
+# HELP pomodorust_ticket_pomodoros Pomodoros recorded per ticket, untracked ones have an empty ticket.
+# TYPE pomodorust_ticket_pomodoros gauge
+pomodorust_ticket_pomodoros{ticket=""} 1
+pomodorust_ticket_pomodoros{ticket="PROJ-1"} 2
+# HELP pomodorust_state State of the timer, 1 for the current one.
+# TYPE pomodorust_state gauge
+pomodorust_state{state="running"} 1
+pomodorust_state{state="break"} 0
+pomodorust_state{state="paused"} 0
+# HELP pomodorust_remaining_seconds Until the pomodoro or break ends.
+# TYPE pomodorust_remaining_seconds gauge
+pomodorust_remaining_seconds 754
+# HELP pomodorust_pomodoros_today Pomodoros recorded today.
+# TYPE pomodorust_pomodoros_today gauge
+pomodorust_pomodoros_today 2
+"#
+    );
+}
+
+#[test]
+fn every_sample_follows_the_type_of_its_metric() {
+    let mut kind = None;
+    for line in sample().render().lines() {
+        if let Some(declared) = line.strip_prefix("# TYPE ") {
+            kind = Some(declared.split(' ').collect::<Vec<_>>());
+        } else if !line.starts_with("# HELP ") {
+            let name = line.split(&['{', ' '][..]).next().unwrap();
+            let kind = kind.as_ref().unwrap();
+            assert_eq!(kind[0], name);
+            // only counters are named _total, and nothing here only goes up
+            assert_eq!(kind[1], "gauge");
+            assert!(!name.ends_with("_total"));
+        }
+    }
+}
+
+#[test]
+fn tickets_are_escaped_in_labels() {
+    let sample = Sample {
+        tickets: vec![(String::from("say \"hi\"\\\n"), 1)],
+        ..sample()
+    };
+    assert!(sample
+        .render()
+        .contains("pomodorust_ticket_pomodoros{ticket=\"say \\\"hi\\\"\\\\\\n\"} 1\n"));
+}
+
+#[test]
+fn only_the_current_state_is_set() {
+    let sample = Sample {
+        status: status("paused", 0),
+        ..sample()
+    };
+    let text = sample.render();
+    assert!(text.contains("pomodorust_state{state=\"running\"} 0\n"));
+    assert!(text.contains("pomodorust_state{state=\"break\"} 0\n"));
+    assert!(text.contains("pomodorust_state{state=\"paused\"} 1\n"));
+}
+
+#[test]
+fn deleted_pomodoros_are_not_counted() {
+    let conn = SqliteConnection::establish(":memory:").unwrap();
+    create_table(&conn).unwrap();
+    let first = pomodoro(&conn, Some("PROJ-1"));
+    pomodoro(&conn, Some("PROJ-1"));
+    pomodoro(&conn, None);
+
+    let before = Sample::gather(&conn, status("break", 300)).unwrap();
+    assert_eq!(before.completed, 3);
+    assert_eq!(before.focused_secs, 3 * 25 * 60);
+
+    Statistic::delete(&conn, first).unwrap();
+    let after = Sample::gather(&conn, status("break", 300)).unwrap();
+    assert_eq!(after.completed, 2);
+    assert_eq!(after.focused_secs, 2 * 25 * 60);
+    let mut tickets = after.tickets;
+    tickets.sort();
+    assert_eq!(
+        tickets,
+        vec![(String::new(), 1), (String::from("PROJ-1"), 1)]
+    );
+}
+
+#[test]
+fn the_textfile_is_replaced_whole() {
+    let path = env::temp_dir().join(format!("pomodorust-{}.prom", process::id()));
+    write_textfile(&path, "old\n").unwrap();
+    write_textfile(&path, &sample().render()).unwrap();
+    assert_eq!(fs::read_to_string(&path).unwrap(), sample().render());
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    assert!(fs::metadata(tmp).is_err());
+    fs::remove_file(path).unwrap();
+}
@@ -0,0 +1,100 @@
+//! Escape sequences notifying about transitions in the terminal.
+
+use pomodorust::notify::{message, sanitize, Notifications};
+use pomodorust::timer::{Durations, State, Timer, Transition, TRANSITIONS};
+use std::time::Duration;
+
+fn on_break() -> Timer {
+    let mut timer = Timer::new(Durations::default(), 1);
+    timer.state = State::NextBreak(Duration::from_secs(5 * 60));
+    timer
+}
+
+fn silent() -> Notifications {
+    Notifications {
+        bell: false,
+        osc9: false,
+        osc777: false,
+        urgent: false,
+        desktop: false,
+    }
+}
+
+fn written(notifications: Notifications, transition: Transition, timer: &Timer) -> String {
+    let mut out = Vec::new();
+    notifications.write(&mut out, transition, timer).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+#[test]
+fn osc_9_and_777_carry_the_message() {
+    let timer = on_break();
+    let osc9 = Notifications {
+        osc9: true,
+        ..silent()
+    };
+    assert_eq!(
+        written(osc9, Transition::PomodoroFinish, &timer),
+        "\x1b]9;Pomodoro finished: Take a 5 minute break.\x07"
+    );
+    let osc777 = Notifications {
+        osc777: true,
+        ..silent()
+    };
+    assert_eq!(
+        written(osc777, Transition::BreakEnd, &timer),
+        "\x1b]777;notify;Break is over;Back to work.\x07"
+    );
+}
+
+#[test]
+fn the_bell_rings_last_and_urgent_marks_the_window() {
+    let timer = on_break();
+    let all = Notifications {
+        bell: true,
+        osc9: true,
+        osc777: true,
+        urgent: true,
+        desktop: false,
+    };
+    assert_eq!(
+        written(all, Transition::BreakEnd, &timer),
+        "\x1b]9;Break is over: Back to work.\x07\
+         \x1b]777;notify;Break is over;Back to work.\x07\
+         \x1b[?1042h\x07"
+    );
+    let bell = Notifications {
+        bell: true,
+        ..silent()
+    };
+    assert_eq!(written(bell, Transition::BreakEnd, &timer), "\x07");
+    assert_eq!(written(silent(), Transition::BreakEnd, &timer), "");
+}
+
+#[test]
+fn separators_and_control_characters_are_stripped() {
+    assert_eq!(sanitize("a;b\x07c\x1b]0\nd\te"), "a,bc]0de");
+    assert_eq!(sanitize("Back to work."), "Back to work.");
+}
+
+#[test]
+fn only_the_end_of_a_pomodoro_or_a_break_is_noticed() {
+    let timer = on_break();
+    let bell = Notifications {
+        bell: true,
+        ..silent()
+    };
+    for &transition in TRANSITIONS.iter() {
+        let noticed = message(transition, &timer).is_some();
+        assert_eq!(
+            noticed,
+            transition == Transition::PomodoroFinish || transition == Transition::BreakEnd,
+            "{}",
+            transition.name()
+        );
+        assert_eq!(written(bell, transition, &timer).is_empty(), !noticed);
+    }
+    // a pomodoro finishes into a break
+    let running = Timer::new(Durations::default(), 1);
+    assert_eq!(message(Transition::PomodoroFinish, &running), None);
+}
@@ -0,0 +1,190 @@
+//! Snapshots of the screens, drawn on tui's test backend. They live as text
+//! in `tests/snapshots`, `UPDATE_SNAPSHOTS=1 cargo test` rewrites them after
+//! an intended change of the layout.
+
+use chrono::NaiveDate;
+use pomodorust::config::Cfg;
+use pomodorust::database::{Pomodoro, Statistic};
+use pomodorust::render;
+use pomodorust::state::App;
+use pomodorust::timer::{Durations, State};
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+use termion::event::Key;
+use tui::backend::TestBackend;
+use tui::Terminal;
+
+fn mins(mins: u64) -> Duration {
+    Duration::from_secs(mins * 60)
+}
+
+fn cfg(working: u64, short_break: u64, long_break: u64) -> Cfg {
+    Cfg::in_memory(Durations {
+        working: mins(working),
+        short_break: mins(short_break),
+        long_break: mins(long_break),
+    })
+    .unwrap()
+}
+
+// the pomodoros of a day that never changes
+fn with_sample_day(cfg: &Cfg, app: &mut App) {
+    let day = NaiveDate::from_ymd(2020, 1, 2);
+    let samples = [
+        ((9, 0), 25, Some("PROJ-1"), None, false),
+        (
+            (9, 30),
+            25,
+            Some("PROJ-1"),
+            Some("review of the parser"),
+            false,
+        ),
+        ((10, 5), 25, None, None, false),
+        (
+            (13, 0),
+            50,
+            Some("PROJ-42"),
+            Some("logged after a meeting"),
+            true,
+        ),
+    ];
+    for ((hour, min), duration, ticket, note, manual) in &samples {
+        let mut stat = Statistic::manual(
+            day.and_hms(*hour, *min, 0),
+            *duration,
+            ticket.map(String::from),
+            note.map(String::from),
+        );
+        stat.manual = *manual;
+        stat.insert(&cfg.conn).unwrap();
+    }
+    app.pomodoros = Pomodoro::pomodoros_between(&cfg.conn, day, day).unwrap();
+    app.selected_pomodoro = Some(1);
+    app.timer.todays_pomodoros = samples.len() as i64;
+}
+
+fn draw(app: &App, cfg: &Cfg, width: u16, height: u16) -> String {
+    let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+    terminal
+        .draw(|mut f| render::draw(&mut f, app, cfg, "snapshot"))
+        .unwrap();
+    let buffer = terminal.backend().buffer();
+    let mut text = String::new();
+    for y in 0..height {
+        let line: String = (0..width)
+            .map(|x| buffer.get(x, y).symbol.as_str())
+            .collect();
+        text.push_str(line.trim_end());
+        text.push('\n');
+    }
+    text
+}
+
+fn assert_snapshot(name: &str, screen: &str) {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/snapshots")
+        .join(format!("{}.txt", name));
+    if env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, screen).unwrap();
+        return;
+    }
+    let expected = fs::read_to_string(&path).unwrap_or_else(|_| {
+        panic!(
+            "no snapshot {}, UPDATE_SNAPSHOTS=1 writes it",
+            path.display()
+        )
+    });
+    assert!(
+        expected == screen,
+        "{} changed, UPDATE_SNAPSHOTS=1 accepts the change\n--- expected\n{}--- drawn\n{}",
+        name,
+        expected,
+        screen
+    );
+}
+
+#[test]
+fn running() {
+    let cfg = cfg(25, 5, 10);
+    let mut app = App::new(&cfg);
+    app.timer.current_pomodoro = mins(10) + Duration::from_secs(30);
+    app.timer.ticket = Some(String::from("PROJ-1"));
+    assert_snapshot("running", &draw(&app, &cfg, 80, 20));
+}
+
+#[test]
+fn paused() {
+    let cfg = cfg(25, 5, 10);
+    let mut app = App::new(&cfg);
+    app.timer.current_pomodoro = mins(20);
+    app.timer.state = State::Paused;
+    assert_snapshot("paused", &draw(&app, &cfg, 80, 20));
+}
+
+#[test]
+fn short_break() {
+    let cfg = cfg(25, 5, 10);
+    let mut app = App::new(&cfg);
+    app.timer.todays_pomodoros = 1;
+    app.timer.state = State::NextBreak(cfg.short_break);
+    app.timer.current_break = mins(1);
+    assert_snapshot("short_break", &draw(&app, &cfg, 80, 20));
+}
+
+#[test]
+fn long_break_of_more_than_an_hour() {
+    let cfg = cfg(50, 10, 75);
+    let mut app = App::new(&cfg);
+    app.timer.todays_pomodoros = 4;
+    app.timer.state = State::NextBreak(cfg.long_break);
+    app.timer.current_break = mins(15);
+    assert_snapshot("long_break", &draw(&app, &cfg, 80, 20));
+}
+
+#[test]
+fn failed_hook() {
+    let cfg = cfg(25, 5, 10);
+    let mut app = App::new(&cfg);
+    app.hook_error = Some(String::from("pomodoro_start: exited with 1"));
+    assert_snapshot("failed_hook", &draw(&app, &cfg, 80, 20));
+}
+
+#[test]
+fn failed_webhook() {
+    let cfg = cfg(25, 5, 10);
+    let mut app = App::new(&cfg);
+    app.integration_error = Some(String::from("webhooks: queue is full"));
+    assert_snapshot("failed_webhook", &draw(&app, &cfg, 80, 20));
+}
+
+#[test]
+fn statistics() {
+    let cfg = cfg(25, 5, 10);
+    let mut app = App::new(&cfg);
+    with_sample_day(&cfg, &mut app);
+    app.selected_tab = 1;
+    assert_snapshot("statistics", &draw(&app, &cfg, 100, 12));
+}
+
+#[test]
+fn statistics_in_a_narrow_terminal() {
+    let cfg = cfg(25, 5, 10);
+    let mut app = App::new(&cfg);
+    with_sample_day(&cfg, &mut app);
+    app.selected_tab = 1;
+    assert_snapshot("statistics_narrow", &draw(&app, &cfg, 80, 12));
+}
+
+#[test]
+fn editing_a_ticket() {
+    let cfg = cfg(25, 5, 10);
+    let mut app = App::new(&cfg);
+    with_sample_day(&cfg, &mut app);
+    app.selected_tab = 1;
+    app.key_handler(&cfg, Key::Char('t')).unwrap();
+    app.key_handler(&cfg, Key::Backspace).unwrap();
+    assert_snapshot("editing_a_ticket", &draw(&app, &cfg, 100, 20));
+}
@@ -0,0 +1,231 @@
+//! Aggregating pomodoros for `stats` and the weekly report.
+
+use chrono::{Duration, NaiveDate, NaiveDateTime};
+use diesel::prelude::Connection;
+use diesel::sqlite::SqliteConnection;
+use pomodorust::database::{create_table, Aggregate, Filter, GroupBy, Pomodoro, Statistic};
+use pomodorust::report::{longest_streak, Report, DEFAULT_TEMPLATE};
+
+fn time(text: &str) -> NaiveDateTime {
+    NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M:%S").unwrap()
+}
+
+fn day(text: &str) -> NaiveDate {
+    text.parse().unwrap()
+}
+
+// 2020-01-05 is a sunday, 2020-01-06 a monday
+fn conn() -> SqliteConnection {
+    let conn = SqliteConnection::establish(":memory:").unwrap();
+    create_table(&conn).unwrap();
+    for &(created_at, ticket, note) in &[
+        ("2019-12-31 23:59:59", Some("PROJ-1"), None),
+        ("2020-01-05 23:59:59", Some("PROJ-1"), Some("parser")),
+        ("2020-01-06 00:00:00", Some("OPS-7"), None),
+        ("2020-01-06 09:25:00", None, Some("mail")),
+    ] {
+        Statistic {
+            created_at: Some(time(created_at)),
+            duration: 25,
+            ticket_id: ticket.map(String::from),
+            note: note.map(String::from),
+            manual: false,
+        }
+        .insert(&conn)
+        .unwrap();
+    }
+    conn
+}
+
+// the groups with their counts
+fn groups(conn: &SqliteConnection, filter: &Filter, group_by: GroupBy) -> Vec<(String, i64)> {
+    Aggregate::aggregates(conn, filter, group_by)
+        .unwrap()
+        .into_iter()
+        .map(|agg| (agg.group.unwrap_or_default(), agg.count))
+        .collect()
+}
+
+fn owned(groups: &[(&str, i64)]) -> Vec<(String, i64)> {
+    groups
+        .iter()
+        .map(|&(group, count)| (String::from(group), count))
+        .collect()
+}
+
+#[test]
+fn days_end_at_midnight() {
+    let conn = conn();
+    assert_eq!(
+        groups(&conn, &Filter::default(), GroupBy::Day),
+        owned(&[("2019-12-31", 1), ("2020-01-05", 1), ("2020-01-06", 2)])
+    );
+}
+
+#[test]
+fn weeks_start_on_monday() {
+    let conn = conn();
+    assert_eq!(
+        groups(&conn, &Filter::default(), GroupBy::Week),
+        owned(&[("2019-12-30", 2), ("2020-01-06", 2)])
+    );
+    assert_eq!(
+        groups(&conn, &Filter::default(), GroupBy::Month),
+        owned(&[("2019-12", 1), ("2020-01", 3)])
+    );
+}
+
+#[test]
+fn ranges_include_both_days() {
+    let conn = conn();
+    let filter = Filter {
+        since: Some(day("2020-01-05")),
+        until: Some(day("2020-01-05")),
+        ticket: None,
+    };
+    assert_eq!(
+        groups(&conn, &filter, GroupBy::Day),
+        owned(&[("2020-01-05", 1)])
+    );
+    let filter = Filter {
+        since: Some(day("2020-01-06")),
+        ..Filter::default()
+    };
+    assert_eq!(
+        groups(&conn, &filter, GroupBy::Ticket),
+        owned(&[("", 1), ("OPS-7", 1)])
+    );
+}
+
+#[test]
+fn tickets_are_matched_by_glob_and_deleted_pomodoros_left_out() {
+    let conn = conn();
+    let filter = Filter {
+        ticket: Some(String::from("PROJ-*")),
+        ..Filter::default()
+    };
+    assert_eq!(
+        groups(&conn, &filter, GroupBy::Ticket),
+        owned(&[("PROJ-1", 2)])
+    );
+    let first =
+        Pomodoro::pomodoros_between(&conn, day("2019-12-31"), day("2019-12-31")).unwrap()[0].id;
+    Statistic::delete(&conn, first).unwrap();
+    assert_eq!(
+        groups(&conn, &filter, GroupBy::Ticket),
+        owned(&[("PROJ-1", 1)])
+    );
+    let aggregates = Aggregate::aggregates(&conn, &Filter::default(), GroupBy::Ticket).unwrap();
+    assert_eq!(aggregates.iter().map(|agg| agg.duration).sum::<i64>(), 75);
+}
+
+fn pomodoro(id: i32, created_at: &str) -> Pomodoro {
+    Pomodoro {
+        id,
+        created_at: time(created_at),
+        duration: 25,
+        ticket_id: None,
+        note: None,
+        manual: false,
+        deleted_at: None,
+    }
+}
+
+#[test]
+fn there_is_no_streak_without_pomodoros() {
+    assert_eq!(longest_streak(&[], Duration::minutes(15)), None);
+}
+
+#[test]
+fn a_streak_goes_on_over_midnight() {
+    let pomodoros = [
+        pomodoro(1, "2020-01-06 23:25:00"),
+        pomodoro(2, "2020-01-06 23:55:00"),
+        pomodoro(3, "2020-01-07 00:25:00"),
+    ];
+    assert_eq!(
+        longest_streak(&pomodoros, Duration::minutes(5)),
+        Some((3, time("2020-01-06 23:00:00"), time("2020-01-07 00:25:00")))
+    );
+    // a gap longer than allowed starts a new one
+    assert_eq!(
+        longest_streak(&pomodoros, Duration::minutes(4)).unwrap().0,
+        1
+    );
+}
+
+#[test]
+fn a_streak_is_broken_by_an_empty_day() {
+    // as long as there is at least a pomodoro a day
+    let max_gap = Duration::days(1);
+    let pomodoros = [
+        pomodoro(1, "2020-01-06 09:25:00"),
+        pomodoro(2, "2020-01-06 09:55:00"),
+        pomodoro(3, "2020-01-08 09:25:00"),
+        pomodoro(4, "2020-01-08 09:55:00"),
+        pomodoro(5, "2020-01-09 09:25:00"),
+    ];
+    assert_eq!(
+        longest_streak(&pomodoros, max_gap),
+        Some((3, time("2020-01-08 09:00:00"), time("2020-01-09 09:25:00")))
+    );
+    // the first of equally long streaks
+    assert_eq!(
+        longest_streak(&pomodoros[..4], max_gap),
+        Some((2, time("2020-01-06 09:00:00"), time("2020-01-06 09:55:00")))
+    );
+}
+
+#[test]
+fn a_report_of_empty_days() {
+    let conn = conn();
+    let report = Report::gather(
+        &conn,
+        day("2020-01-01"),
+        day("2020-01-04"),
+        Duration::minutes(15),
+    )
+    .unwrap();
+    let text = report.render(DEFAULT_TEMPLATE);
+    assert!(text.starts_with("# Pomodoros 2020-01-01 – 2020-01-04\n"));
+    assert!(text.contains("**0** pomodoros, **0:00** hours of focus."));
+    assert!(text.contains("## Per day\n\nNo pomodoros.\n"));
+    assert!(text.contains("- Longest streak: none\n"));
+    assert!(text.contains("- Abandoned pomodoros: 0\n"));
+    assert!(text.trim_end().ends_with("## Notes\n\nNo notes."));
+}
+
+#[test]
+fn a_report_of_a_week() {
+    let conn = conn();
+    let report = Report::gather(
+        &conn,
+        day("2019-12-30"),
+        day("2020-01-06"),
+        Duration::minutes(15),
+    )
+    .unwrap();
+    let text = report.render("{{total}}\n{{days}}\n{{tickets}}\n{{streak}}\n{{notes}}");
+    assert_eq!(
+        text,
+        "**4** pomodoros, **1:40** hours of focus.
+| Day | Pomodoros | Time |
+|---|---:|---:|
+| 2019-12-31 | 1 | 0:25 |
+| 2020-01-05 | 1 | 0:25 |
+| 2020-01-06 | 2 | 0:50 |
+| Ticket | Pomodoros | Time |
+|---|---:|---:|
+| - | 1 | 0:25 |
+| OPS-7 | 1 | 0:25 |
+| PROJ-1 | 2 | 0:50 |
+2 pomodoros from 2020-01-05 23:34 to 00:00
+### PROJ-1
+
+- parser
+
+### Without ticket
+
+- mail"
+    );
+}
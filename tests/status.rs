@@ -0,0 +1,106 @@
+//! The status file and what status bars are shown of it.
+
+use pomodorust::status::{Status, DEFAULT_FORMAT};
+use pomodorust::timer::{Durations, State, Timer};
+use serde_json::{json, Value};
+use std::env;
+use std::fs;
+use std::process;
+use std::time::{Duration, SystemTime};
+
+fn status(state: &str) -> Status {
+    Status {
+        state: String::from(state),
+        remaining_secs: 754,
+        count: 3,
+        ticket: Some(String::from("PROJ-1")),
+        updated_at: 0,
+    }
+}
+
+fn json(text: String) -> Value {
+    serde_json::from_str(&text).unwrap()
+}
+
+#[test]
+fn placeholders_are_replaced() {
+    let status = status("running");
+    assert_eq!(status.format(DEFAULT_FORMAT), "running 12:34 3");
+    assert_eq!(
+        status.format("[{ticket}] {remaining} ({count}) {state}"),
+        "[PROJ-1] 12:34 (3) running"
+    );
+    // without a ticket nothing is left at the end
+    let untracked = Status {
+        ticket: None,
+        ..status
+    };
+    assert_eq!(untracked.format("{remaining} {ticket}"), "12:34");
+}
+
+#[test]
+fn a_stopped_timer_has_no_time_left() {
+    let stopped = Status::stopped();
+    assert_eq!(stopped.remaining(), "--:--");
+    assert_eq!(stopped.format(DEFAULT_FORMAT), "stopped --:-- 0");
+    let mut timer = Timer::new(Durations::default(), 2);
+    timer.state = State::NextBreak(Duration::from_secs(5 * 60));
+    assert_eq!(Status::of(&timer).format("{state} {count}"), "break 2");
+}
+
+#[test]
+fn i3bar_blocks_are_colored_by_state() {
+    assert_eq!(
+        json(status("running").i3bar("{remaining}")),
+        json!({ "full_text": "12:34", "short_text": "12:34", "color": "#5f87d7" })
+    );
+    for (state, color) in &[
+        ("break", "#87af87"),
+        ("paused", "#d75f5f"),
+        ("stopped", "#808080"),
+    ] {
+        assert_eq!(json(status(state).i3bar(DEFAULT_FORMAT))["color"], *color);
+    }
+    assert_eq!(
+        json(Status::stopped().i3bar(DEFAULT_FORMAT))["short_text"],
+        "--:--"
+    );
+}
+
+#[test]
+fn waybar_gets_the_state_as_class() {
+    assert_eq!(
+        json(status("paused").waybar("{ticket} {remaining}")),
+        json!({
+            "text": "PROJ-1 12:34",
+            "alt": "paused",
+            "class": "paused",
+            "tooltip": "3 pomodoros today",
+        })
+    );
+}
+
+#[test]
+fn a_status_not_written_for_10_seconds_is_stopped() {
+    let path = env::temp_dir().join(format!("pomodorust-status-{}.json", process::id()));
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let written = |updated_at| {
+        let status = Status {
+            updated_at,
+            ..status("running")
+        };
+        fs::write(&path, serde_json::to_vec(&status).unwrap()).unwrap();
+        Status::read_from(&path)
+    };
+    assert_eq!(written(now - 8).state, "running");
+    assert_eq!(written(now - 10).state, "stopped");
+    assert_eq!(written(now - 3600).state, "stopped");
+
+    fs::write(&path, "{").unwrap();
+    assert_eq!(Status::read_from(&path).state, "stopped");
+    fs::remove_file(&path).unwrap();
+    assert_eq!(Status::read_from(&path).state, "stopped");
+}
@@ -0,0 +1,189 @@
+//! Work and break cycles of the timer core, which needs neither a terminal
+//! nor a database.
+
+use pomodorust::timer::{Durations, Record, State, Timer, Transition};
+use std::time::Duration;
+
+fn mins(mins: u64) -> Duration {
+    Duration::from_secs(mins * 60)
+}
+
+fn timer() -> Timer {
+    let mut timer = Timer::new(Durations::default(), 0);
+    timer.take_transitions();
+    timer
+}
+
+fn transitions(timer: &mut Timer) -> Vec<Transition> {
+    timer
+        .take_transitions()
+        .into_iter()
+        .map(|(transition, _)| transition)
+        .collect()
+}
+
+// ticks of a second, as the event loop sends them
+fn run_for(timer: &mut Timer, duration: Duration) -> Vec<Record> {
+    (0..duration.as_secs())
+        .filter_map(|_| timer.tick(Duration::from_secs(1)).unwrap())
+        .collect()
+}
+
+#[test]
+fn a_new_timer_starts_a_pomodoro() {
+    let mut timer = Timer::new(Durations::default(), 0);
+    assert_eq!(timer.state, State::Running);
+    assert_eq!(
+        timer.take_transitions(),
+        vec![(Transition::PomodoroStart, mins(25))]
+    );
+}
+
+#[test]
+fn a_pomodoro_is_followed_by_a_short_break() {
+    let mut timer = timer();
+    timer.ticket = Some(String::from("PROJ-1"));
+    assert_eq!(
+        run_for(&mut timer, mins(25) - Duration::from_secs(1)),
+        vec![]
+    );
+    assert_eq!(timer.remaining(), Duration::from_secs(1));
+
+    let records = run_for(&mut timer, Duration::from_secs(1));
+    assert_eq!(
+        records,
+        vec![Record::Finished {
+            minutes: 25,
+            ticket: Some(String::from("PROJ-1")),
+        }]
+    );
+    assert_eq!(timer.state, State::NextBreak(mins(5)));
+    assert_eq!(timer.todays_pomodoros, 1);
+    assert_eq!(
+        timer.take_transitions(),
+        vec![
+            (Transition::PomodoroFinish, mins(25)),
+            (Transition::BreakStart, mins(5)),
+        ]
+    );
+}
+
+#[test]
+fn the_next_pomodoro_starts_after_the_break() {
+    let mut timer = timer();
+    run_for(&mut timer, mins(25));
+    timer.take_transitions();
+    run_for(&mut timer, mins(5));
+    assert_eq!(timer.state, State::Running);
+    assert_eq!(timer.remaining(), mins(25));
+    assert_eq!(
+        timer.take_transitions(),
+        vec![
+            (Transition::BreakEnd, mins(5)),
+            (Transition::PomodoroStart, mins(25)),
+        ]
+    );
+}
+
+#[test]
+fn every_fourth_pomodoro_is_followed_by_a_long_break() {
+    let mut timer = timer();
+    let mut breaks = Vec::new();
+    for _ in 0..8 {
+        run_for(&mut timer, mins(25));
+        breaks.push(timer.state);
+        let remaining = timer.remaining();
+        run_for(&mut timer, remaining);
+    }
+    let (short, long) = (State::NextBreak(mins(5)), State::NextBreak(mins(10)));
+    assert_eq!(
+        breaks,
+        vec![short, short, short, long, short, short, short, long]
+    );
+    assert_eq!(timer.todays_pomodoros, 8);
+}
+
+#[test]
+fn the_count_of_the_day_decides_the_long_break() {
+    let mut timer = Timer::new(Durations::default(), 3);
+    run_for(&mut timer, mins(25));
+    assert_eq!(timer.state, State::NextBreak(mins(10)));
+}
+
+#[test]
+fn a_paused_pomodoro_keeps_its_time() {
+    let mut timer = timer();
+    run_for(&mut timer, mins(10));
+    timer.toggle_pause();
+    assert_eq!(run_for(&mut timer, mins(60)), vec![]);
+    assert_eq!(timer.remaining(), mins(15));
+    timer.toggle_pause();
+    assert_eq!(
+        transitions(&mut timer),
+        vec![Transition::Pause, Transition::Resume]
+    );
+    assert_eq!(run_for(&mut timer, mins(15)).len(), 1);
+}
+
+#[test]
+fn resuming_after_a_break_was_paused_starts_a_pomodoro() {
+    let mut timer = timer();
+    run_for(&mut timer, mins(25));
+    timer.toggle_pause();
+    timer.take_transitions();
+    timer.resume();
+    assert_eq!(timer.state, State::Running);
+    assert_eq!(
+        transitions(&mut timer),
+        vec![Transition::Resume, Transition::PomodoroStart]
+    );
+}
+
+#[test]
+fn only_breaks_can_be_skipped() {
+    let mut timer = timer();
+    assert!(!timer.skip());
+    run_for(&mut timer, mins(25));
+    run_for(&mut timer, mins(2));
+    timer.take_transitions();
+    assert!(timer.skip());
+    assert_eq!(timer.state, State::Running);
+    assert_eq!(timer.current_break, Duration::from_secs(0));
+    assert_eq!(
+        transitions(&mut timer),
+        vec![Transition::BreakEnd, Transition::PomodoroStart]
+    );
+}
+
+#[test]
+fn abandoning_records_the_whole_minutes_worked() {
+    let mut timer = timer();
+    run_for(&mut timer, Duration::from_secs(150));
+    assert_eq!(
+        timer.abandon().unwrap(),
+        Some(Record::Abandoned {
+            minutes: 2,
+            ticket: None,
+        })
+    );
+    assert_eq!(timer.remaining(), mins(25));
+    assert_eq!(
+        transitions(&mut timer),
+        vec![Transition::Abandon, Transition::PomodoroStart]
+    );
+}
+
+#[test]
+fn less_than_a_minute_is_not_recorded() {
+    let mut timer = timer();
+    run_for(&mut timer, Duration::from_secs(59));
+    assert_eq!(timer.abandon().unwrap(), None);
+}
+
+#[test]
+fn quitting_starts_no_pomodoro() {
+    let mut timer = timer();
+    run_for(&mut timer, mins(3));
+    assert!(timer.quit().unwrap().is_some());
+    assert_eq!(transitions(&mut timer), vec![Transition::Abandon]);
+}
@@ -0,0 +1,146 @@
+//! Webhook deliveries against a local stand-in for the endpoint.
+
+use pomodorust::webhook::{queue_path, Webhooks};
+use serde_json::{json, Value};
+use std::env;
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const RETRY: Duration = Duration::from_millis(50);
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+// answers with the given statuses in turn and passes on the bodies it got
+fn stand_in(statuses: Vec<u16>) -> (String, mpsc::Receiver<(u16, Value)>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/pomodoros", listener.local_addr().unwrap());
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        for (stream, status) in listener.incoming().zip(statuses) {
+            let mut stream = stream.unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line.trim().is_empty() {
+                    break;
+                }
+                let lower = line.to_lowercase();
+                if let Some(value) = lower.strip_prefix("content-length:") {
+                    length = value.trim().parse().unwrap();
+                }
+            }
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+            write!(
+                stream,
+                "HTTP/1.1 {} Whatever\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                status
+            )
+            .unwrap();
+            let _ = tx.send((status, serde_json::from_slice(&body).unwrap()));
+        }
+    });
+    (url, rx)
+}
+
+fn db_path(name: &str) -> PathBuf {
+    let path = env::temp_dir().join(format!("pomodorust-{}-{}.db", name, std::process::id()));
+    let _ = fs::remove_file(queue_path(&path));
+    path
+}
+
+fn wait_until(done: impl Fn() -> bool) {
+    let started = Instant::now();
+    while !done() {
+        assert!(started.elapsed() < TIMEOUT, "timed out");
+        thread::sleep(RETRY);
+    }
+}
+
+#[test]
+fn retries_until_delivered() {
+    let (url, requests) = stand_in(vec![503, 503, 200]);
+    let db = db_path("retries");
+    let webhooks = Webhooks::start(&[url], queue_path(&db), RETRY).unwrap();
+    let payload = json!({"duration": 25, "ticket_id": "PROJ-42"});
+    webhooks.send(&payload).unwrap();
+
+    for status in &[503, 503, 200] {
+        assert_eq!(
+            requests.recv_timeout(TIMEOUT).unwrap(),
+            (*status, payload.clone())
+        );
+    }
+    wait_until(|| webhooks.pending().is_empty());
+    assert!(!queue_path(&db).exists());
+}
+
+#[test]
+fn client_errors_are_dropped() {
+    let (url, requests) = stand_in(vec![400]);
+    let db = db_path("rejected");
+    let webhooks = Webhooks::start(&[url], queue_path(&db), RETRY).unwrap();
+    webhooks.send(&json!({"duration": 25})).unwrap();
+
+    assert_eq!(requests.recv_timeout(TIMEOUT).unwrap().0, 400);
+    wait_until(|| webhooks.pending().is_empty());
+}
+
+#[test]
+fn timeouts_and_rate_limits_are_retried() {
+    let (url, requests) = stand_in(vec![429, 408, 200]);
+    let db = db_path("rate-limited");
+    let webhooks = Webhooks::start(&[url], queue_path(&db), RETRY).unwrap();
+    webhooks.send(&json!({"duration": 25})).unwrap();
+
+    for status in &[429, 408, 200] {
+        assert_eq!(requests.recv_timeout(TIMEOUT).unwrap().0, *status);
+    }
+    wait_until(|| webhooks.pending().is_empty());
+}
+
+#[test]
+fn queue_survives_a_restart() {
+    let (url, requests) = stand_in(vec![503, 200]);
+    let db = db_path("restart");
+    let payload = json!({"duration": 25});
+    {
+        // would not retry before the test is over
+        let later = Duration::from_secs(3600);
+        let webhooks = Webhooks::start(std::slice::from_ref(&url), queue_path(&db), later).unwrap();
+        webhooks.send(&payload).unwrap();
+        assert_eq!(requests.recv_timeout(TIMEOUT).unwrap().0, 503);
+    }
+    // the first worker notices it is dropped
+    thread::sleep(RETRY);
+    assert!(queue_path(&db).exists());
+
+    let webhooks = Webhooks::start(&[url], queue_path(&db), RETRY).unwrap();
+    assert_eq!(requests.recv_timeout(TIMEOUT).unwrap(), (200, payload));
+    wait_until(|| webhooks.pending().is_empty());
+}
+
+#[test]
+fn deliveries_sent_at_once_have_ids_of_their_own() {
+    let db = db_path("ids");
+    let urls = [
+        String::from("http://127.0.0.1:9/a"),
+        String::from("http://127.0.0.1:9/b"),
+    ];
+    let webhooks = Webhooks::start(&urls, queue_path(&db), Duration::from_secs(3600)).unwrap();
+    for duration in 0..100 {
+        webhooks.send(&json!({ "duration": duration })).unwrap();
+    }
+    let mut ids: Vec<u128> = webhooks.pending().iter().map(|d| d.id).collect();
+    ids.sort_unstable();
+    ids.dedup();
+    assert_eq!(ids.len(), 200);
+    drop(webhooks);
+    let _ = fs::remove_file(queue_path(&db));
+}